@@ -1,9 +1,10 @@
 use crate::path::OsPath;
 use crate::{config, shell};
+use std::rc::Rc;
 use termion::color::{Color, Rgb};
 
 pub trait Behaviour {
-    fn render(&self, input: &[char], term: &mut shell::Terminal);
+    fn render(&self, input: &str, term: &mut dyn shell::Terminal);
 }
 
 pub trait Colorizer {
@@ -11,14 +12,12 @@ pub trait Colorizer {
 }
 
 pub struct ExecutableWordColorizer {
-    os_path: OsPath,
+    os_path: Rc<OsPath>,
 }
 
 impl ExecutableWordColorizer {
-    pub fn new() -> Self {
-        Self {
-            os_path: OsPath::load(),
-        }
+    pub fn new(os_path: Rc<OsPath>) -> Self {
+        Self { os_path }
     }
 }
 
@@ -38,7 +37,7 @@ impl Colorizer for ExecutableWordColorizer {
 
 pub struct WordColorizeBehaviour {
     config: config::Colorize,
-    colorizer: Vec<Box<Colorizer>>,
+    colorizer: Vec<Box<dyn Colorizer>>,
 }
 
 impl WordColorizeBehaviour {
@@ -49,7 +48,7 @@ impl WordColorizeBehaviour {
         }
     }
 
-    pub fn add_colorizer(&mut self, colorizer: Box<Colorizer>) {
+    pub fn add_colorizer(&mut self, colorizer: Box<dyn Colorizer>) {
         self.colorizer.push(colorizer);
     }
 
@@ -57,25 +56,75 @@ impl WordColorizeBehaviour {
         self.colorizer
             .iter()
             .find_map(|colorizer| colorizer.colorize(word, &self.config))
+            .or_else(|| self.text_color())
+    }
+
+    fn text_color(&self) -> Option<Rgb> {
+        self.config.text.map(|config::Rgb(r, g, b)| Rgb(r, g, b))
     }
 }
 
 impl Behaviour for WordColorizeBehaviour {
-    fn render(&self, input: &[char], term: &mut shell::Terminal) {
-        let mut word = String::new();
-        for ch in input.iter() {
-            term.in_color(None).write(*ch);
+    fn render(&self, input: &str, term: &mut dyn shell::Terminal) {
+        let text_color = self.text_color();
+        for (row, line) in input.split('\n').enumerate() {
+            if row > 0 {
+                term.in_color(None).write('\n');
+            }
 
-            if *ch == ' ' {
-                word.clear();
-            } else {
-                word.push(*ch);
-                let color = self.find_color_for(&word);
-                let color: Option<&Color> = color.as_ref().map(|color| color as &dyn Color);
+            for (index, word) in line.split(' ').enumerate() {
+                if index > 0 {
+                    let color: Option<&dyn Color> =
+                        text_color.as_ref().map(|color| color as &dyn Color);
+                    term.in_color(color).write(' ');
+                }
 
-                term.cursor().move_left(word.len() as u16).clear_after();
-                term.in_color(color).write_text(&word);
+                if !word.is_empty() {
+                    let color = self.find_color_for(word);
+                    let color: Option<&dyn Color> = color.as_ref().map(|color| color as &dyn Color);
+                    term.in_color(color).write_text(word);
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTerminal;
+    use crate::my::screen::Pen;
+
+    struct Ls;
+
+    impl Colorizer for Ls {
+        fn colorize(&self, word: &str, _: &config::Colorize) -> Option<Rgb> {
+            if word == "ls" {
+                Some(Rgb(0, 204, 0))
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn words_get_the_color_of_their_colorizer() {
+        let mut behaviour = WordColorizeBehaviour::new(&config::Colorize {
+            command: None,
+            text: Some(config::Rgb(1, 2, 3)),
+            selection: None,
+        });
+        behaviour.add_colorizer(Box::new(Ls));
+
+        let mut term = MockTerminal::new(20, 2);
+        behaviour.render("ls  -la", &mut term);
+
+        let text = Some(Pen::new(&Rgb(1, 2, 3)));
+        assert_eq!(term.lines(), ["ls  -la"]);
+        assert_eq!(term.pen_at(0, 0), Some(Pen::new(&Rgb(0, 204, 0))));
+        assert_eq!(term.pen_at(1, 0), Some(Pen::new(&Rgb(0, 204, 0))));
+        assert_eq!(term.pen_at(2, 0), text);
+        assert_eq!(term.pen_at(3, 0), text);
+        assert_eq!(term.pen_at(4, 0), text);
+    }
+}
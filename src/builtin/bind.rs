@@ -0,0 +1,116 @@
+use crate::builtin::Builtin;
+use crate::my::keymap::{self, Action, Binding, Keymap};
+use crate::shell;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const USAGE: &str = "usage: bind [-r] <keys>... [<action> | -- <command>...]";
+
+/// Lists, adds and removes the bindings of keys while the shell runs.
+pub struct Bind {
+    keymap: Rc<RefCell<Keymap>>,
+}
+
+impl Bind {
+    pub fn new(keymap: Rc<RefCell<Keymap>>) -> Self {
+        Self { keymap }
+    }
+
+    fn list(&self, term: &mut dyn shell::Terminal) {
+        for (keys, binding) in self.keymap.borrow().bindings() {
+            term.in_color(None).write_text(&format!(
+                "bind {} {}",
+                keymap::key_names(keys),
+                binding
+            ));
+            term.newline();
+        }
+    }
+
+    fn fail(&self, message: &str, term: &mut dyn shell::Terminal) {
+        term.in_color(None)
+            .write_text(&format!("bind: {}", message));
+        term.newline();
+    }
+
+    /// Binds the keys in front of the last argument to that action, or the keys in front of
+    /// `--` to the command behind it.
+    fn bind(&self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        let (keys, binding) = match args.iter().position(|arg| *arg == "--") {
+            Some(index) if index + 1 < args.len() => {
+                let command = args[index + 1..].join(" ");
+                (&args[..index], Binding::Command(command))
+            }
+            Some(_) => {
+                self.fail("no command given", term);
+                return 2;
+            }
+            None => {
+                let (name, keys) = args.split_last().expect("bind without arguments");
+                match Action::from_name(name) {
+                    Some(action) => (keys, Binding::Action(action)),
+                    None => {
+                        let actions: Vec<&str> = Action::names().collect();
+                        self.fail(
+                            &format!("unknown action {}, one of {}", name, actions.join(", ")),
+                            term,
+                        );
+                        return 1;
+                    }
+                }
+            }
+        };
+
+        match keymap::parse_keys(&keys.join(" ")) {
+            Ok(keys) => {
+                self.keymap.borrow_mut().bind(keys, binding);
+                0
+            }
+            Err(e) => {
+                self.fail(&e, term);
+                1
+            }
+        }
+    }
+
+    fn unbind(&self, keys: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        let keys = match keymap::parse_keys(&keys.join(" ")) {
+            Ok(keys) => keys,
+            Err(e) => {
+                self.fail(&e, term);
+                return 1;
+            }
+        };
+
+        if self.keymap.borrow_mut().unbind(&keys) {
+            0
+        } else {
+            self.fail(&format!("{} is not bound", keymap::key_names(&keys)), term);
+            1
+        }
+    }
+}
+
+impl Builtin for Bind {
+    fn name(&self) -> &str {
+        "bind"
+    }
+
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        match args {
+            [] => {
+                self.list(term);
+                0
+            }
+            ["-r", keys @ ..] if !keys.is_empty() => self.unbind(keys, term),
+            [keys @ .., _] if !keys.is_empty() && !args[0].starts_with('-') => {
+                self.bind(args, term)
+            }
+            _ => {
+                term.in_color(None).write_text(USAGE);
+                term.newline();
+                2
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::builtin::Builtin;
+use crate::shell;
+use std::io::ErrorKind;
+use std::process::{Command, Stdio};
+
+const USAGE: &str = "usage: clip <command> [<arg>...]";
+
+/// Runs a command and copies what it prints to the clipboard of the terminal, since the output
+/// of a command cannot be piped yet.
+pub struct Clip;
+
+impl Clip {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn write_lines(&self, output: &[u8], term: &mut dyn shell::Terminal) {
+        for line in String::from_utf8_lossy(output).lines() {
+            term.in_color(None).write_text(line);
+            term.newline();
+        }
+    }
+}
+
+impl Builtin for Clip {
+    fn name(&self) -> &str {
+        "clip"
+    }
+
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        let (name, args) = match args.split_first() {
+            Some((name, args)) => (name, args),
+            None => {
+                term.in_color(None).write_text(USAGE);
+                term.newline();
+                return 2;
+            }
+        };
+
+        let output = Command::new(name).args(args).stdin(Stdio::null()).output();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                let (message, code) = if e.kind() == ErrorKind::NotFound {
+                    (format!("clip: command not found: {}", name), 127)
+                } else {
+                    (format!("clip: {}: {}", name, e), 126)
+                };
+                term.in_color(None).write_text(&message);
+                term.newline();
+
+                return code;
+            }
+        };
+
+        self.write_lines(&output.stdout, term);
+        self.write_lines(&output.stderr, term);
+        term.copy_to_clipboard(&String::from_utf8_lossy(&output.stdout));
+
+        output.status.code().unwrap_or(1)
+    }
+}
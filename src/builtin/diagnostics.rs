@@ -0,0 +1,43 @@
+use crate::builtin::Builtin;
+use crate::path::OsPath;
+use crate::shell;
+use std::rc::Rc;
+
+/// Shows what kept directories of PATH or files in them out of the executables, like a missing
+/// directory, one without permission or a dangling link.
+pub struct Diagnostics {
+    /// The executables the shell indexed
+    os_path: Rc<OsPath>,
+}
+
+impl Diagnostics {
+    pub fn new(os_path: Rc<OsPath>) -> Self {
+        Self { os_path }
+    }
+}
+
+impl Builtin for Diagnostics {
+    fn name(&self) -> &str {
+        "diagnostics"
+    }
+
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        if !args.is_empty() {
+            term.in_color(None).write_text("usage: diagnostics");
+            term.newline();
+            return 2;
+        }
+
+        for problem in self.os_path.problems() {
+            term.in_color(None)
+                .write_text(&format!("PATH: {}", problem));
+            term.newline();
+        }
+
+        if self.os_path.problems().is_empty() {
+            0
+        } else {
+            1
+        }
+    }
+}
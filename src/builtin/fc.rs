@@ -0,0 +1,135 @@
+use crate::builtin::Builtin;
+use crate::editor;
+use crate::my::History;
+use crate::shell;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const USAGE: &str = "usage: fc [-l] [-s | -e <editor>] [<entry>]";
+/// The number of entries `fc -l` lists
+const LISTED: usize = 16;
+
+/// Lists the history, or edits an entry and runs it again. An entry is its number, a negative
+/// offset from the newest one or the start of a command, the newest entry by default.
+pub struct Fc {
+    history: Rc<RefCell<History>>,
+    /// The commands the shell runs after this builtin
+    queue: Rc<RefCell<Vec<String>>>,
+    /// The editor of the config, `-e` takes another one
+    editor: Option<String>,
+}
+
+impl Fc {
+    pub fn new(
+        history: Rc<RefCell<History>>,
+        queue: Rc<RefCell<Vec<String>>>,
+        editor: Option<String>,
+    ) -> Self {
+        Self {
+            history,
+            queue,
+            editor,
+        }
+    }
+
+    fn fail(&self, message: &str, term: &mut dyn shell::Terminal) -> i32 {
+        term.in_color(None).write_text(&format!("fc: {}", message));
+        term.newline();
+
+        1
+    }
+
+    fn list(&self, term: &mut dyn shell::Terminal) {
+        let history = self.history.borrow();
+        let entries = history.entries();
+        let start = entries.len().saturating_sub(LISTED);
+
+        for (index, entry) in entries.iter().enumerate().skip(start) {
+            for (row, line) in entry.lines().enumerate() {
+                let text = if row == 0 {
+                    format!("{:>5}  {}", index + 1, line)
+                } else {
+                    format!("{:>5}  {}", "", line)
+                };
+                term.in_color(None).write_text(&text);
+                term.newline();
+            }
+        }
+    }
+
+    fn find(&self, entry: Option<&str>) -> Option<String> {
+        let history = self.history.borrow();
+        let entries = history.entries();
+
+        let index = match entry {
+            None => entries.len().checked_sub(1),
+            Some(entry) => match entry.parse::<isize>() {
+                Ok(number) if number > 0 => Some(number as usize - 1),
+                Ok(number) => entries.len().checked_sub(number.unsigned_abs()),
+                Err(_) => entries
+                    .iter()
+                    .rposition(|command| command.starts_with(entry)),
+            },
+        };
+
+        index.and_then(|index| entries.get(index)).cloned()
+    }
+}
+
+impl Builtin for Fc {
+    fn name(&self) -> &str {
+        "fc"
+    }
+
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        let (editor, edit, entry) = match args {
+            ["-l"] => {
+                self.list(term);
+                return 0;
+            }
+            [] => (None, true, None),
+            ["-s"] => (None, false, None),
+            ["-s", entry] => (None, false, Some(*entry)),
+            ["-e", editor] => (Some(*editor), true, None),
+            ["-e", editor, entry] => (Some(*editor), true, Some(*entry)),
+            [entry] if !entry.starts_with('-') || entry.parse::<isize>().is_ok() => {
+                (None, true, Some(*entry))
+            }
+            _ => {
+                term.in_color(None).write_text(USAGE);
+                term.newline();
+                return 2;
+            }
+        };
+
+        let command = match self.find(entry) {
+            Some(command) => command,
+            None => {
+                let message = match entry {
+                    Some(entry) => format!("no entry {}", entry),
+                    None => String::from("the history is empty"),
+                };
+                return self.fail(&message, term);
+            }
+        };
+
+        let command = if edit {
+            term.suspend();
+            let edited = editor::edit(&command, editor.or(self.editor.as_deref()));
+            term.resume();
+
+            match edited {
+                Ok(edited) => edited,
+                Err(e) => return self.fail(&e.to_string(), term),
+            }
+        } else {
+            command
+        };
+
+        if !command.trim().is_empty() {
+            self.queue.borrow_mut().push(command);
+        }
+
+        0
+    }
+}
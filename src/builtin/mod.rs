@@ -0,0 +1,45 @@
+pub mod bind;
+pub mod clip;
+pub mod diagnostics;
+pub mod fc;
+pub mod set;
+pub mod theme;
+pub mod which;
+
+pub use self::bind::Bind;
+pub use self::clip::Clip;
+pub use self::diagnostics::Diagnostics;
+pub use self::fc::Fc;
+pub use self::set::Set;
+pub use self::theme::Theme;
+pub use self::which::Which;
+
+use crate::shell;
+
+pub trait Builtin {
+    fn name(&self) -> &str;
+    /// Runs the builtin and returns its exit code.
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32;
+}
+
+pub struct Builtins {
+    builtins: Vec<Box<dyn Builtin>>,
+}
+
+impl Builtins {
+    pub fn new() -> Self {
+        Self {
+            builtins: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, builtin: Box<dyn Builtin>) {
+        self.builtins.push(builtin);
+    }
+
+    pub fn find(&mut self, name: &str) -> Option<&mut Box<dyn Builtin>> {
+        self.builtins
+            .iter_mut()
+            .find(|builtin| builtin.name() == name)
+    }
+}
@@ -0,0 +1,65 @@
+use crate::builtin::Builtin;
+use crate::config::Editing;
+use crate::shell;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Switches the options of the shell, for now the keys the line is edited with.
+pub struct Set {
+    editing: Rc<Cell<Editing>>,
+}
+
+impl Set {
+    pub fn new(editing: Rc<Cell<Editing>>) -> Self {
+        Self { editing }
+    }
+
+    fn list(&self, term: &mut dyn shell::Terminal) {
+        for (name, editing) in [("emacs", Editing::Emacs), ("vi", Editing::Vi)] {
+            let state = if self.editing.get() == editing {
+                "on"
+            } else {
+                "off"
+            };
+
+            term.in_color(None)
+                .write_text(&format!("{:<8}{}", name, state));
+            term.newline();
+        }
+    }
+}
+
+impl Builtin for Set {
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        match args {
+            [] | ["-o"] | ["+o"] => {
+                self.list(term);
+                0
+            }
+            ["-o", "emacs"] | ["+o", "vi"] => {
+                self.editing.set(Editing::Emacs);
+                0
+            }
+            ["-o", "vi"] | ["+o", "emacs"] => {
+                self.editing.set(Editing::Vi);
+                0
+            }
+            ["-o" | "+o", name] => {
+                term.in_color(None)
+                    .write_text(&format!("set: unknown option {}", name));
+                term.newline();
+                1
+            }
+            _ => {
+                term.in_color(None)
+                    .write_text("usage: set [-o | +o] [emacs | vi]");
+                term.newline();
+                2
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::behaviour::{Colorizer, WordColorizeBehaviour};
+use crate::builtin::Builtin;
+use crate::config::{self, Config};
+use crate::drawable::Drawable;
+use crate::shell::{Line, Terminal};
+use crate::theme::Themes;
+use crate::{my, shell};
+use termion::color::Rgb;
+
+const SAMPLE_COMMAND: &str = "ls";
+const SAMPLE_LINE: &str = "ls -la ~/projects";
+
+/// Colors the sample command of the preview, so it does not depend on the PATH.
+struct SampleColorizer;
+
+impl Colorizer for SampleColorizer {
+    fn colorize(&self, word: &str, config: &config::Colorize) -> Option<Rgb> {
+        if word == SAMPLE_COMMAND {
+            config.command.map(|config::Rgb(r, g, b)| Rgb(r, g, b))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Theme {
+    config: Config,
+    themes: Themes,
+}
+
+impl Theme {
+    pub fn new(config: &Config, themes: Themes) -> Self {
+        Self {
+            config: config.clone(),
+            themes,
+        }
+    }
+
+    fn list(&self, term: &mut dyn shell::Terminal) {
+        for name in self.themes.names() {
+            let marker = if name == self.config.theme.name {
+                "* "
+            } else {
+                "  "
+            };
+
+            term.in_color(None)
+                .write_text(&format!("{}{}", marker, name));
+            term.newline();
+        }
+    }
+
+    fn preview(&self, name: &str, term: &mut dyn shell::Terminal) {
+        let config = self.themes.apply(name, &self.config);
+
+        term.in_color(None).write_text(&format!("[{}]", name));
+        term.newline();
+
+        // Drawn like the real prompt, then written out as it looks
+        let (columns, height) = term.size();
+        let mut screen = my::Screen::new(columns, height);
+
+        let mut prompt = shell::Prompt::new(&config.prompt);
+        prompt.refresh(&shell::Context::default());
+        prompt.render_on(&mut screen);
+
+        let mut behaviour = WordColorizeBehaviour::new(&config.colorize);
+        behaviour.add_colorizer(Box::new(SampleColorizer));
+
+        let mut line = my::Line::new(&config.line);
+        line.add_behaviour(Box::new(behaviour));
+        line.set_padding(screen.cursor());
+        for ch in SAMPLE_LINE.chars() {
+            line.insert(ch);
+        }
+
+        line.render_on(&mut screen);
+        screen.render_on(term);
+        term.newline();
+    }
+}
+
+impl Builtin for Theme {
+    fn name(&self) -> &str {
+        "theme"
+    }
+
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        match args {
+            [] | ["list"] => {
+                self.list(term);
+                0
+            }
+            ["preview"] => {
+                for name in self.themes.names() {
+                    self.preview(name, term);
+                }
+                0
+            }
+            ["preview", name] if self.themes.get(name).is_some() => {
+                self.preview(name, term);
+                0
+            }
+            ["preview", name] => {
+                term.in_color(None)
+                    .write_text(&format!("theme: unknown theme {}", name));
+                term.newline();
+                1
+            }
+            _ => {
+                term.in_color(None)
+                    .write_text("usage: theme [list | preview [<name>]]");
+                term.newline();
+                2
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::builtin::Builtin;
+use crate::path::OsPath;
+use crate::shell;
+use std::rc::Rc;
+
+const USAGE: &str = "usage: which [-a] <name>...";
+
+/// Shows the file a command runs, or with `-a` every file of that name in PATH order.
+pub struct Which {
+    /// The executables the shell indexed
+    os_path: Rc<OsPath>,
+}
+
+impl Which {
+    pub fn new(os_path: Rc<OsPath>) -> Self {
+        Self { os_path }
+    }
+}
+
+impl Builtin for Which {
+    fn name(&self) -> &str {
+        "which"
+    }
+
+    fn run(&mut self, args: &[&str], term: &mut dyn shell::Terminal) -> i32 {
+        let (all, names) = match args {
+            ["-a", names @ ..] => (true, names),
+            names => (false, names),
+        };
+        if names.is_empty() || names.iter().any(|name| name.starts_with('-')) {
+            term.in_color(None).write_text(USAGE);
+            term.newline();
+            return 2;
+        }
+
+        let mut code = 0;
+        for name in names {
+            let files = match self.os_path.find_all(name) {
+                [] => {
+                    term.in_color(None)
+                        .write_text(&format!("{} not found", name));
+                    term.newline();
+                    code = 1;
+                    continue;
+                }
+                files if all => files,
+                [first, ..] => std::slice::from_ref(first),
+            };
+
+            for file in files {
+                term.in_color(None).write_text(&file.display().to_string());
+                term.newline();
+            }
+        }
+
+        code
+    }
+}
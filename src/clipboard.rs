@@ -0,0 +1,104 @@
+//! The clipboard of the terminal, reached with OSC 52 escape sequences. They work over SSH and
+//! need no clipboard of the platform, but some terminals only allow to copy.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BEL: u8 = 0x07;
+const ESC: u8 = 0x1B;
+
+/// Asks the terminal for the clipboard, it answers on stdin like `copy` would set it.
+pub const REQUEST: &str = "\x1b]52;c;?\x07";
+
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | u32::from(*byte) << (16 - 8 * index)
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (group >> (18 - 6 * index)) & 0x3F;
+                encoded.push(char::from(ALPHABET[sextet as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let mut group = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes().filter(|byte| *byte != b'=') {
+        let sextet = ALPHABET.iter().position(|letter| *letter == byte)?;
+        group = group << 6 | sextet as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+            group &= (1 << bits) - 1;
+        }
+    }
+
+    Some(bytes)
+}
+
+/// The sequence which puts `text` into the clipboard.
+pub fn copy(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", encode(text.as_bytes()))
+}
+
+/// Returns the length of the OSC sequence at the start of `bytes`, which ends with BEL or ST,
+/// or `None` if it is not complete yet.
+pub fn sequence_len(bytes: &[u8]) -> Option<usize> {
+    let end = bytes
+        .iter()
+        .enumerate()
+        .skip(2)
+        .find(|(index, byte)| **byte == BEL || (**byte == b'\\' && bytes[index - 1] == ESC))
+        .map(|(index, _)| index)?;
+
+    Some(end + 1)
+}
+
+/// The text of a clipboard sequence, like the answer to `REQUEST`.
+pub fn parse(sequence: &[u8]) -> Option<String> {
+    let sequence = std::str::from_utf8(sequence).ok()?;
+    let body = sequence.strip_prefix("\x1b]52;")?;
+    let body = body
+        .strip_suffix('\x07')
+        .or_else(|| body.strip_suffix("\x1b\\"))?;
+    let (_, data) = body.split_once(';')?;
+
+    decode(data).map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_goes_through_the_clipboard() {
+        assert_eq!(copy("hi"), "\x1b]52;c;aGk=\x07");
+        assert_eq!(encode(b"mysh"), "bXlzaA==");
+        assert_eq!(encode(b"foo"), "Zm9v");
+
+        for text in ["", "a", "ab", "abc", "echo ünïcode\n"] {
+            assert_eq!(parse(copy(text).as_bytes()).as_deref(), Some(text));
+        }
+    }
+
+    #[test]
+    fn an_answer_ends_with_bel_or_st() {
+        let answer = b"\x1b]52;c;aGk=\x1b\\rest";
+
+        assert_eq!(sequence_len(answer), Some(13));
+        assert_eq!(parse(&answer[..13]).as_deref(), Some("hi"));
+        assert_eq!(sequence_len(b"\x1b]52;c;aG"), None);
+        assert_eq!(parse(b"\x1b]52;c;!\x07"), None);
+    }
+}
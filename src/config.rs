@@ -1,6 +1,7 @@
 use serde_derive::Deserialize;
+use std::collections::HashMap;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 pub struct Rgb(pub u8, pub u8, pub u8);
 
 impl Default for Rgb {
@@ -13,12 +14,293 @@ fn render_always() -> bool {
     true
 }
 
+fn default_true() -> bool {
+    true
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct User {
     #[serde(default = "render_always")]
     pub render: bool,
+    pub color: Option<Rgb>,
+}
+
+impl Default for User {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Host {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+    /// Shows the fully qualified domain name instead of the short hostname
     #[serde(default)]
-    pub color: Rgb,
+    pub fqdn: bool,
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+            fqdn: false,
+        }
+    }
+}
+
+fn default_time_format() -> String {
+    String::from("%H:%M:%S")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Time {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+    /// A strftime format
+    #[serde(default = "default_time_format")]
+    pub format: String,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+            format: default_time_format(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Venv {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+}
+
+impl Default for Venv {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+        }
+    }
+}
+
+fn default_nix() -> String {
+    String::from("nix-shell")
+}
+
+fn default_direnv() -> String {
+    String::from("direnv")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Environment {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+    #[serde(default = "default_nix")]
+    pub nix: String,
+    #[serde(default = "default_direnv")]
+    pub direnv: String,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+            nix: default_nix(),
+            direnv: default_direnv(),
+        }
+    }
+}
+
+fn default_ssh_symbol() -> String {
+    String::from("ssh")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Ssh {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+    #[serde(default = "default_ssh_symbol")]
+    pub symbol: String,
+}
+
+impl Default for Ssh {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+            symbol: default_ssh_symbol(),
+        }
+    }
+}
+
+fn default_git_timeout() -> u64 {
+    200
+}
+
+fn default_placeholder() -> String {
+    String::from("…")
+}
+
+fn default_ahead() -> String {
+    String::from("↑")
+}
+
+fn default_behind() -> String {
+    String::from("↓")
+}
+
+fn default_staged() -> String {
+    String::from("+")
+}
+
+fn default_dirty() -> String {
+    String::from("!")
+}
+
+fn default_untracked() -> String {
+    String::from("?")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Git {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+    /// Milliseconds to wait for `git status` before only the branch is shown
+    #[serde(default = "default_git_timeout")]
+    pub timeout: u64,
+    /// Shown instead of the markers while `git status` is still running
+    #[serde(default = "default_placeholder")]
+    pub placeholder: String,
+    #[serde(default = "default_ahead")]
+    pub ahead: String,
+    #[serde(default = "default_behind")]
+    pub behind: String,
+    #[serde(default = "default_staged")]
+    pub staged: String,
+    #[serde(default = "default_dirty")]
+    pub dirty: String,
+    #[serde(default = "default_untracked")]
+    pub untracked: String,
+}
+
+impl Default for Git {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+            timeout: default_git_timeout(),
+            placeholder: default_placeholder(),
+            ahead: default_ahead(),
+            behind: default_behind(),
+            staged: default_staged(),
+            dirty: default_dirty(),
+            untracked: default_untracked(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Status {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+    #[serde(default)]
+    pub symbol: String,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+            symbol: String::new(),
+        }
+    }
+}
+
+fn default_threshold() -> u64 {
+    2000
+}
+
+fn default_duration_prefix() -> String {
+    String::from("took ")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Duration {
+    #[serde(default = "render_always")]
+    pub render: bool,
+    pub color: Option<Rgb>,
+    /// Milliseconds a command has to run before its duration is shown
+    #[serde(default = "default_threshold")]
+    pub threshold: u64,
+    #[serde(default = "default_duration_prefix")]
+    pub prefix: String,
+}
+
+impl Default for Duration {
+    fn default() -> Self {
+        Self {
+            render: true,
+            color: None,
+            threshold: default_threshold(),
+            prefix: default_duration_prefix(),
+        }
+    }
+}
+
+fn default_ellipsis() -> String {
+    String::from("…")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Location {
+    pub color: Option<Rgb>,
+    /// Shows the home directory as `~`
+    #[serde(default = "default_true")]
+    pub tilde: bool,
+    /// Starts the path at the root of the git repository
+    #[serde(default)]
+    pub repo_root: bool,
+    /// Keeps only the last components
+    pub components: Option<usize>,
+    /// Shortens the parent components to their first character like fish does
+    #[serde(default)]
+    pub abbreviate: bool,
+    /// Cuts the path from the front once it is wider
+    pub max_width: Option<usize>,
+    /// Marks where something was cut off
+    #[serde(default = "default_ellipsis")]
+    pub ellipsis: String,
+}
+
+impl Default for Location {
+    fn default() -> Self {
+        Self {
+            color: None,
+            tilde: true,
+            repo_root: false,
+            components: None,
+            abbreviate: false,
+            max_width: None,
+            ellipsis: default_ellipsis(),
+        }
+    }
 }
 
 fn default_user_caret() -> String {
@@ -37,8 +319,12 @@ pub struct Caret {
     pub user: String,
     #[serde(default = "default_admin_caret")]
     pub admin: String,
-    #[serde(default)]
-    pub color: Rgb,
+    pub color: Option<Rgb>,
+    pub admin_color: Option<Rgb>,
+    /// Members of this group get the admin caret as well, e.g. "wheel" or "sudo"
+    pub admin_group: Option<String>,
+    /// The caret in the normal and visual mode of vi, instead of the user or admin one
+    pub normal: Option<String>,
     #[serde(default)]
     pub on_newline: bool,
 }
@@ -49,12 +335,24 @@ impl Default for Caret {
             render: true,
             user: default_user_caret(),
             admin: default_admin_caret(),
-            color: Rgb::default(),
+            color: None,
+            admin_color: None,
+            admin_group: None,
+            normal: None,
             on_newline: false,
         }
     }
 }
 
+/// The keys the line is edited with, `set -o` switches them at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Editing {
+    #[default]
+    Emacs,
+    Vi,
+}
+
 fn default_padding() -> u8 {
     1
 }
@@ -62,10 +360,15 @@ fn default_padding() -> u8 {
 #[derive(Debug, Clone, Deserialize)]
 pub struct Line {
     pub capacity: u16,
+    /// Empty columns between the prompt and the line
     #[serde(default = "default_padding")]
     pub left_padding: u8,
     #[serde(default)]
     pub color: Rgb,
+    #[serde(default)]
+    pub editing: Editing,
+    /// The editor of ctrl-x ctrl-e and `fc`, instead of the one of $VISUAL or $EDITOR
+    pub editor: Option<String>,
 }
 
 impl Default for Line {
@@ -74,34 +377,82 @@ impl Default for Line {
             capacity: 1024,
             left_padding: default_padding(),
             color: Rgb::default(),
+            editing: Editing::default(),
+            editor: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Prompt {
+    pub format: Option<String>,
+    /// The widgets in the order they are shown, if no format is given
+    pub widgets: Option<Vec<String>>,
+    /// The widgets shown right-aligned on the input row, hidden once the line reaches them
+    pub right: Option<Vec<String>>,
+    /// Shrinks the prompt of a submitted command down to its caret
+    #[serde(default)]
+    pub transient: bool,
+    /// Written in front of a widget, unless it is the first one
+    #[serde(default)]
+    pub separators: HashMap<String, String>,
     pub user: Option<User>,
+    pub host: Option<Host>,
+    pub time: Option<Time>,
+    pub venv: Option<Venv>,
+    pub env: Option<Environment>,
+    pub ssh: Option<Ssh>,
+    pub git: Option<Git>,
+    pub status: Option<Status>,
+    pub duration: Option<Duration>,
+    #[serde(default)]
+    pub location: Location,
     #[serde(default)]
     pub caret: Caret,
 }
 
-impl Default for Prompt {
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Colorize {
+    pub command: Option<Rgb>,
+    pub text: Option<Rgb>,
+    /// The text selected with the mouse
+    pub selection: Option<Rgb>,
+}
+
+fn default_theme() -> String {
+    String::from("default")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Theme {
+    #[serde(default = "default_theme")]
+    pub name: String,
+}
+
+impl Default for Theme {
     fn default() -> Self {
         Self {
-            user: None,
-            caret: Caret::default(),
+            name: default_theme(),
         }
     }
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
-pub struct Colorize {
-    pub command: Option<Rgb>,
+/// What a key or a sequence of keys does, an editor action like "kill-line" or a command.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Binding {
+    Action(String),
+    Command { command: String },
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Config {
     pub prompt: Prompt,
     pub line: Line,
     pub colorize: Colorize,
+    #[serde(default)]
+    pub theme: Theme,
+    /// Bindings by their keys, like "ctrl-r" or "ctrl-x ctrl-e"
+    #[serde(default)]
+    pub keys: HashMap<String, Binding>,
 }
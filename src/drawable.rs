@@ -1,5 +1,5 @@
 use crate::shell;
 
 pub trait Drawable {
-    fn render_on(&self, terminal: &mut shell::Terminal);
+    fn render_on(&self, terminal: &mut dyn shell::Terminal);
 }
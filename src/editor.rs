@@ -0,0 +1,98 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, process};
+
+/// Tells the temporary files of one shell apart
+static EDITS: AtomicUsize = AtomicUsize::new(0);
+
+/// The editor of the user from `$VISUAL` or `$EDITOR` with its arguments, vi if there is none.
+fn command(editor: Option<&str>) -> Vec<String> {
+    let configured = || {
+        ["VISUAL", "EDITOR"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.trim().is_empty())
+    };
+
+    editor
+        .map(str::to_owned)
+        .or_else(configured)
+        .map(|editor| editor.split_whitespace().map(str::to_owned).collect())
+        .filter(|words: &Vec<String>| !words.is_empty())
+        .unwrap_or_else(|| vec![String::from("vi")])
+}
+
+/// Creates a new file for `text`, named like a script so editors highlight it as one. Only the
+/// user may read it, since command lines often hold secrets.
+fn create(text: &str) -> io::Result<PathBuf> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let count = EDITS.fetch_add(1, Ordering::Relaxed);
+    let path = env::temp_dir().join(format!("mysh-{}-{}.sh", process::id(), count));
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    writeln!(file, "{}", text)?;
+
+    Ok(path)
+}
+
+fn run(editor: &[String], path: &Path) -> io::Result<()> {
+    let status = Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(path)
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} failed with {}",
+            editor[0], status
+        )))
+    }
+}
+
+/// Opens `text` in `editor` or the one of the user and returns what was saved, without the line
+/// breaks at the end. The editor gets the terminal, so raw mode has to be left before.
+pub fn edit(text: &str, editor: Option<&str>) -> io::Result<String> {
+    let editor = command(editor);
+    let path = create(text)?;
+
+    let edited = run(&editor, &path).and_then(|_| fs::read_to_string(&path));
+    let _ = fs::remove_file(&path);
+
+    Ok(edited?.trim_end_matches('\n').to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_editor_changes_the_text() {
+        assert_eq!(
+            edit("echo hi\necho hi", Some("sed -i s/hi/ho/")).unwrap(),
+            "echo ho\necho ho"
+        );
+        assert_eq!(edit("ls", Some("true")).unwrap(), "ls");
+        assert!(edit("ls", Some("false")).is_err());
+    }
+
+    #[test]
+    fn only_the_user_may_read_the_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = create("export TOKEN=secret").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,144 @@
+use log::{debug, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Head {
+    Branch(String),
+    Detached(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    pub ahead: u32,
+    pub behind: u32,
+    pub staged: bool,
+    pub dirty: bool,
+    pub untracked: bool,
+}
+
+/// Searches `dir` and its parents for the `.git` directory (or `.git` file of a worktree).
+fn find_git_dir(dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        let git = dir.join(".git");
+        if git.is_dir() {
+            return Some(git);
+        }
+
+        if git.is_file() {
+            let content = fs::read_to_string(&git).ok()?;
+            let target = content.trim().strip_prefix("gitdir:")?.trim();
+
+            return Some(dir.join(target));
+        }
+    }
+
+    None
+}
+
+/// Returns the top directory of the work tree `dir` belongs to.
+pub fn root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|dir| dir.join(".git").exists())
+}
+
+/// Reads the head of the repository `dir` belongs to. This only touches `.git` and is cheap.
+pub fn head(dir: &Path) -> Option<Head> {
+    read_head(&find_git_dir(dir)?)
+}
+
+fn read_head(git_dir: &Path) -> Option<Head> {
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    if let Some(reference) = head.strip_prefix("ref:") {
+        let reference = reference.trim();
+        let branch = reference.strip_prefix("refs/heads/").unwrap_or(reference);
+
+        Some(Head::Branch(branch.to_owned()))
+    } else {
+        Some(Head::Detached(head.chars().take(7).collect()))
+    }
+}
+
+/// Runs `git status` and gives up if it does not finish within `timeout`.
+fn run_status(dir: &Path, timeout: Duration) -> Option<String> {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+    use std::thread;
+    use std::time::Instant;
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain=v2", "--branch"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // Read on a separate thread, so a full pipe cannot block the child
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output).ok()
+    });
+
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(Some(status)) => {
+                debug!("git status failed with {}", status);
+                return None;
+            }
+            Ok(None) if start.elapsed() < timeout => thread::sleep(Duration::from_millis(5)),
+            Ok(None) => {
+                warn!("git status took longer than {:?}", timeout);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Err(e) => {
+                warn!("Unable to wait for git status: {}", e);
+                return None;
+            }
+        }
+    }
+
+    reader.join().ok()?
+}
+
+fn parse_status(output: &str) -> Status {
+    let mut status = Status::default();
+
+    for line in output.lines() {
+        let mut fields = line.split(' ');
+        match fields.next() {
+            // # branch.ab +<ahead> -<behind>
+            Some("#") if fields.next() == Some("branch.ab") => {
+                let mut count = || fields.next().and_then(|n| n.get(1..)?.parse().ok());
+                status.ahead = count().unwrap_or(0);
+                status.behind = count().unwrap_or(0);
+            }
+            Some("1") | Some("2") => {
+                let xy: Vec<char> = fields.next().unwrap_or("..").chars().collect();
+                status.staged |= xy.first().map(|x| *x != '.').unwrap_or(false);
+                status.dirty |= xy.get(1).map(|y| *y != '.').unwrap_or(false);
+            }
+            Some("u") => status.dirty = true,
+            Some("?") => status.untracked = true,
+            _ => {}
+        }
+    }
+
+    status
+}
+
+/// Gathers the status of the repository `dir` belongs to, which may take a while in huge repositories.
+pub fn status(dir: &Path, timeout: Duration) -> Option<Status> {
+    find_git_dir(dir)?;
+
+    run_status(dir, timeout).map(|output| parse_status(&output))
+}
@@ -7,12 +7,22 @@ use crate::config::Config;
 use crate::shell::Shell;
 
 mod behaviour;
+mod builtin;
+mod clipboard;
 mod config;
 mod drawable;
+mod editor;
+mod git;
+#[cfg(test)]
+mod mock;
 mod my;
 mod path;
 mod pool;
 mod shell;
+mod signal;
+mod system;
+mod text;
+mod theme;
 
 fn load_config() -> std::io::Result<String> {
     let mut file = File::open("mysh.toml")?;
@@ -0,0 +1,456 @@
+use crate::clipboard;
+use crate::my::screen::{Cell, Pen};
+use crate::my::terminal::Device;
+use crate::shell::{self, CursorPos};
+use crate::text;
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use termion::color::{self, Color};
+
+/// An in-memory terminal of `columns` x `height` cells for tests. It can be drawn on through
+/// the `shell::Terminal` traits, or be fed the escape sequences the shell writes, see `device`.
+pub struct MockTerminal {
+    columns: usize,
+    height: usize,
+    cells: Vec<Vec<Cell>>,
+    cursor_pos: CursorPos,
+    saved_pos: CursorPos,
+    /// A full row keeps the cursor on its last column until the next character arrives
+    pending_wrap: bool,
+    pen: Option<Pen>,
+    /// Every position the cursor was moved to, as column and row counted from 1
+    pub moves: Vec<(u16, u16)>,
+    /// What was copied to the clipboard last
+    pub clipboard: Option<String>,
+    bytes: Vec<u8>,
+}
+
+fn blank() -> Cell {
+    Cell {
+        text: String::from(" "),
+        pen: None,
+    }
+}
+
+impl MockTerminal {
+    pub fn new(columns: u16, height: u16) -> Self {
+        let columns = usize::from(columns);
+        let height = usize::from(height);
+
+        Self {
+            columns,
+            height,
+            cells: vec![vec![blank(); columns]; height],
+            cursor_pos: CursorPos::new(),
+            saved_pos: CursorPos::new(),
+            pending_wrap: false,
+            pen: None,
+            moves: Vec::new(),
+            clipboard: None,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// A terminal the shell can write to, while the test keeps looking at it.
+    pub fn shared(columns: u16, height: u16) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self::new(columns, height)))
+    }
+
+    pub fn device(terminal: &Rc<RefCell<Self>>) -> Box<dyn Device> {
+        Box::new(MockDevice(Rc::clone(terminal)))
+    }
+
+    /// The text of every row without trailing blanks, leaving out the empty rows at the end.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .cells
+            .iter()
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.text.as_str()).collect();
+                line.trim_end().to_owned()
+            })
+            .collect();
+
+        while lines.last().map(String::is_empty).unwrap_or(false) {
+            lines.pop();
+        }
+
+        lines
+    }
+
+    /// The color of the cell at column `x` and row `y`, both counted from 0.
+    pub fn pen_at(&self, x: usize, y: usize) -> Option<Pen> {
+        self.cells[y][x].pen.clone()
+    }
+
+    /// The cursor as column and row, both counted from 0.
+    pub fn cursor_cell(&self) -> (usize, usize) {
+        let (x, y) = self.cursor_pos.get();
+        (usize::from(x), usize::from(y) - 1)
+    }
+
+    fn move_cursor(&mut self, x: usize, y: usize) {
+        let x = x.min(self.columns - 1);
+        let y = y.clamp(1, self.height);
+
+        self.pending_wrap = false;
+        self.cursor_pos.x = x as u16;
+        self.cursor_pos.y = y as u16;
+        self.moves.push((x as u16 + 1, y as u16));
+    }
+
+    fn line_feed(&mut self) {
+        self.pending_wrap = false;
+        if usize::from(self.cursor_pos.y) < self.height {
+            self.cursor_pos.y += 1;
+        } else {
+            self.cells.remove(0);
+            self.cells.push(vec![blank(); self.columns]);
+        }
+    }
+
+    fn put(&mut self, grapheme: &str) {
+        let width = text::grapheme_width(grapheme);
+        let (x, row) = self.cursor_cell();
+
+        if width == 0 {
+            if let Some(cell) = x.checked_sub(1).map(|x| &mut self.cells[row][x]) {
+                cell.text += grapheme;
+            }
+            return;
+        }
+
+        if self.pending_wrap || x + width > self.columns {
+            self.cursor_pos.x = 0;
+            self.line_feed();
+        }
+
+        let (x, row) = self.cursor_cell();
+        self.cells[row][x] = Cell {
+            text: grapheme.to_owned(),
+            pen: self.pen.clone(),
+        };
+        if width > 1 && x + 1 < self.columns {
+            self.cells[row][x + 1] = Cell {
+                text: String::new(),
+                pen: self.pen.clone(),
+            };
+        }
+
+        if x + width >= self.columns {
+            self.cursor_pos.x = (self.columns - 1) as u16;
+            self.pending_wrap = true;
+        } else {
+            self.cursor_pos.x = (x + width) as u16;
+        }
+    }
+
+    fn clear_line_after(&mut self) {
+        let (x, row) = self.cursor_cell();
+        for cell in self.cells[row][x..].iter_mut() {
+            *cell = blank();
+        }
+    }
+
+    fn clear_screen_after(&mut self) {
+        self.clear_line_after();
+
+        let (_, row) = self.cursor_cell();
+        for cells in self.cells[row + 1..].iter_mut() {
+            *cells = vec![blank(); self.columns];
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        for grapheme in text::graphemes(text) {
+            match grapheme {
+                "\r" => {
+                    self.pending_wrap = false;
+                    self.cursor_pos.x = 0;
+                }
+                "\n" => self.line_feed(),
+                "\r\n" => {
+                    self.cursor_pos.x = 0;
+                    self.line_feed();
+                }
+                _ => self.put(grapheme),
+            }
+        }
+    }
+
+    fn select_graphic(&mut self, params: &[u16]) {
+        match params {
+            [] | [0] | [39] => self.pen = None,
+            [38, 2, r, g, b] => {
+                self.pen = Some(Pen::new(&color::Rgb(*r as u8, *g as u8, *b as u8)));
+            }
+            [38, 5, value] => self.pen = Some(Pen::new(&color::AnsiValue(*value as u8))),
+            _ => {}
+        }
+    }
+
+    fn control(&mut self, params: &str, action: char) {
+        if params.starts_with('?') {
+            // Modes like mouse reporting do not change the screen
+            return;
+        }
+
+        let params: Vec<u16> = params
+            .split(';')
+            .filter(|param| !param.is_empty())
+            .filter_map(|param| param.parse().ok())
+            .collect();
+        let count = usize::from(params.first().copied().unwrap_or(1));
+        let (x, y) = self.cursor_cell();
+
+        match action {
+            'A' => self.move_cursor(x, (y + 1).saturating_sub(count)),
+            'B' => self.move_cursor(x, y + 1 + count),
+            'C' => self.move_cursor(x + count, y + 1),
+            'D' => self.move_cursor(x.saturating_sub(count), y + 1),
+            'H' => {
+                let row = usize::from(params.first().copied().unwrap_or(1));
+                let column = usize::from(params.get(1).copied().unwrap_or(1));
+                self.move_cursor(column.max(1) - 1, row);
+            }
+            'J' if params.first() == Some(&2) => shell::Clear::all(self),
+            'J' => self.clear_screen_after(),
+            'K' => self.clear_line_after(),
+            'm' => self.select_graphic(&params),
+            's' => self.saved_pos = self.cursor_pos,
+            'u' => {
+                let (x, y) = self.saved_pos.get();
+                self.move_cursor(usize::from(x), usize::from(y));
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies the text and escape sequences in `bytes`, keeping an incomplete rest for later.
+    fn feed(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+
+        let mut start = 0;
+        while start < self.bytes.len() {
+            let rest = &self.bytes[start..];
+            if rest[0] != 0x1B {
+                let end = rest
+                    .iter()
+                    .position(|byte| *byte == 0x1B)
+                    .unwrap_or(rest.len());
+                let valid = match std::str::from_utf8(&rest[..end]) {
+                    Ok(valid) => valid.len(),
+                    Err(e) => e.valid_up_to(),
+                };
+                if valid == 0 {
+                    break;
+                }
+
+                let text = String::from_utf8_lossy(&rest[..valid]).into_owned();
+                self.print(&text);
+                start += valid;
+                continue;
+            }
+
+            match rest.get(1) {
+                Some(b'[') => {}
+                Some(b']') => match clipboard::sequence_len(rest) {
+                    Some(len) => {
+                        if let Some(text) = clipboard::parse(&rest[..len]) {
+                            self.clipboard = Some(text);
+                        }
+                        start += len;
+                        continue;
+                    }
+                    None => break,
+                },
+                // Other escapes do not change the screen
+                Some(_) => {
+                    start += 2;
+                    continue;
+                }
+                None => break,
+            }
+
+            let end = match rest
+                .iter()
+                .skip(2)
+                .position(|byte| (0x40..=0x7E).contains(byte))
+            {
+                Some(end) => end + 2,
+                None => break,
+            };
+
+            let params = String::from_utf8_lossy(&rest[2..end]).into_owned();
+            self.control(&params, char::from(rest[end]));
+            start += end + 1;
+        }
+
+        self.bytes.drain(..start);
+    }
+}
+
+impl shell::Write for MockTerminal {
+    fn write(&mut self, ch: char) {
+        self.put(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    fn write_text(&mut self, text: &str) {
+        self.print(text);
+        self.pen = None;
+    }
+}
+
+impl shell::Terminal for MockTerminal {
+    fn clear(&mut self) -> &mut dyn shell::Clear {
+        self
+    }
+
+    fn cursor(&mut self) -> &mut dyn shell::Cursor {
+        self
+    }
+
+    fn in_color(&mut self, color: Option<&dyn Color>) -> &mut dyn shell::Write {
+        self.pen = color.map(Pen::new);
+
+        self
+    }
+
+    fn newline(&mut self) -> &mut dyn shell::Terminal {
+        self.cursor_pos.x = 0;
+        self.line_feed();
+
+        self
+    }
+
+    fn flush(&mut self) -> &mut dyn shell::Terminal {
+        self
+    }
+
+    fn size(&self) -> (u16, u16) {
+        (self.columns as u16, self.height as u16)
+    }
+
+    fn copy_to_clipboard(&mut self, text: &str) {
+        self.clipboard = Some(text.to_owned());
+    }
+
+    fn suspend(&mut self) {}
+
+    fn resume(&mut self) {}
+}
+
+impl shell::Clear for MockTerminal {
+    fn all(&mut self) {
+        self.cells = vec![vec![blank(); self.columns]; self.height];
+        self.move_cursor(0, 1);
+    }
+
+    fn line(&mut self) {
+        let (_, y) = self.cursor_cell();
+        self.cells[y] = vec![blank(); self.columns];
+        self.move_cursor(0, y + 1);
+    }
+}
+
+impl shell::Cursor for MockTerminal {
+    fn set_to(&mut self, x: u16, y: u16) -> &mut dyn shell::Cursor {
+        self.move_cursor(usize::from(x), usize::from(y));
+
+        self
+    }
+
+    /// Like `my::Terminal` a full row reports the start of the next one.
+    fn get(&self) -> (u16, u16) {
+        if self.pending_wrap {
+            (0, self.cursor_pos.y + 1)
+        } else {
+            self.cursor_pos.get()
+        }
+    }
+
+    fn clear_after(&mut self) -> &mut dyn shell::Cursor {
+        self.clear_screen_after();
+
+        self
+    }
+
+    fn set_x(&mut self, x: u16) -> &mut dyn shell::Cursor {
+        let (_, y) = self.cursor_pos.get();
+        self.move_cursor(usize::from(x), usize::from(y));
+
+        self
+    }
+
+    fn set_y(&mut self, y: u16) -> &mut dyn shell::Cursor {
+        let (x, _) = self.cursor_pos.get();
+        self.move_cursor(usize::from(x), usize::from(y));
+
+        self
+    }
+
+    fn move_left(&mut self, x: u16) -> &mut dyn shell::Cursor {
+        self.control(&x.to_string(), 'D');
+
+        self
+    }
+
+    fn move_right(&mut self, x: u16) -> &mut dyn shell::Cursor {
+        self.control(&x.to_string(), 'C');
+
+        self
+    }
+
+    fn move_up(&mut self, y: u16) -> &mut dyn shell::Cursor {
+        self.control(&y.to_string(), 'A');
+
+        self
+    }
+
+    fn move_down(&mut self, y: u16) -> &mut dyn shell::Cursor {
+        self.control(&y.to_string(), 'B');
+
+        self
+    }
+
+    fn save(&mut self) -> &mut dyn shell::Cursor {
+        self.control("", 's');
+
+        self
+    }
+
+    fn restore(&mut self) -> &mut dyn shell::Cursor {
+        self.control("", 'u');
+
+        self
+    }
+}
+
+struct MockDevice(Rc<RefCell<MockTerminal>>);
+
+impl io::Write for MockDevice {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().feed(bytes);
+
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Device for MockDevice {
+    fn suspend(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(shell::Terminal::size(&*self.0.borrow()))
+    }
+}
@@ -0,0 +1,65 @@
+use log::debug;
+
+/// The lines entered so far. Browsing starts behind the newest entry, where the line being typed
+/// is kept until browsing comes back to it.
+pub struct History {
+    entries: Vec<String>,
+    index: usize,
+    draft: String,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            index: 0,
+            draft: String::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Adds `line` unless it is empty or repeats the newest entry, and stops browsing.
+    pub fn add(&mut self, line: &str) {
+        if !line.trim().is_empty() && self.entries.last().map(String::as_str) != Some(line) {
+            debug!("Add {} to the history", line);
+            self.entries.push(line.to_owned());
+        }
+
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.index = self.entries.len();
+        self.draft.clear();
+    }
+
+    /// Goes back to the next older entry. `current` is kept when browsing starts.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        if self.index == 0 {
+            return None;
+        }
+
+        if self.index == self.entries.len() {
+            self.draft = current.to_owned();
+        }
+        self.index -= 1;
+
+        Some(&self.entries[self.index])
+    }
+
+    /// Goes forward to the next newer entry and finally to the line typed before browsing.
+    pub fn next(&mut self) -> Option<&str> {
+        if self.index >= self.entries.len() {
+            return None;
+        }
+
+        self.index += 1;
+        match self.entries.get(self.index) {
+            Some(entry) => Some(entry),
+            None => Some(&self.draft),
+        }
+    }
+}
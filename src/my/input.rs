@@ -0,0 +1,339 @@
+use crate::clipboard;
+use log::debug;
+use std::collections::VecDeque;
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+use termion::event::{self, Key, MouseEvent};
+
+const ESC: u8 = 0x1B;
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Key(Key),
+    /// A click, drag or release, at a column and row counted from 1
+    Mouse(MouseEvent),
+    /// Answer to a cursor position request, 1-based like `cursor::Goto`
+    CursorPos(u16, u16),
+    /// The terminal changed its size
+    Resize,
+    /// Text pasted in bracketed paste mode, with the line breaks as `\n`
+    Paste(String),
+    /// Answer to a clipboard request
+    Clipboard(String),
+}
+
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0xF0..=0xFF => 4,
+        0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        _ => 1,
+    }
+}
+
+/// Returns the length of the first sequence in `bytes` or `None` if it is not complete yet.
+fn sequence_len(bytes: &[u8]) -> Option<usize> {
+    let len = match bytes {
+        [] => return None,
+        // A lone ESC is the Esc key, since sequences arrive in one piece
+        [ESC] => 1,
+        [ESC, b'[', b'M', ..] => 6,
+        [ESC, b'[', rest @ ..] => {
+            let end = rest.iter().position(|byte| (0x40..=0x7E).contains(byte))?;
+            end + 3
+        }
+        [ESC, b'O', ..] => 3,
+        [ESC, b']', ..] => clipboard::sequence_len(bytes)?,
+        [ESC, byte, ..] => 1 + utf8_len(*byte),
+        [byte, ..] => utf8_len(*byte),
+    };
+
+    if len <= bytes.len() {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+/// Returns the text of a bracketed paste at the start of `bytes` and the length of the paste
+/// with its brackets, or `None` if the end has not arrived yet.
+fn parse_paste(bytes: &[u8]) -> Option<(String, usize)> {
+    let content = &bytes[PASTE_START.len()..];
+    let end = content
+        .windows(PASTE_END.len())
+        .position(|window| window == PASTE_END)?;
+
+    // Terminals send the line breaks of a paste as carriage returns
+    let text = String::from_utf8_lossy(&content[..end])
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+
+    Some((text, PASTE_START.len() + end + PASTE_END.len()))
+}
+
+fn parse_cursor_pos(sequence: &[u8]) -> Option<Event> {
+    let params = std::str::from_utf8(sequence.get(2..sequence.len() - 1)?).ok()?;
+    let mut params = params.split(';').map(|n| n.parse::<u16>().ok());
+    let y = params.next()??;
+    let x = params.next()??;
+
+    Some(Event::CursorPos(x, y))
+}
+
+/// Parses one sequence. `ESC [ row ; column R` is the answer to a cursor position request only
+/// while one is awaited, otherwise it is F3 with modifiers, like Shift-F3 `ESC [ 1 ; 2 R`.
+fn parse_sequence(sequence: &[u8], cursor_pos: bool) -> Option<Event> {
+    // termion takes a lone ESC for an incomplete sequence
+    if sequence == [ESC] {
+        return Some(Event::Key(Key::Esc));
+    }
+
+    if sequence.starts_with(&[ESC, b']']) {
+        return clipboard::parse(sequence).map(Event::Clipboard);
+    }
+
+    if sequence.len() > 2 && sequence.starts_with(&[ESC, b'[']) && sequence.ends_with(b"R") {
+        return match parse_cursor_pos(sequence)? {
+            Event::CursorPos(..) if !cursor_pos => Some(Event::Key(Key::F(3))),
+            event => Some(event),
+        };
+    }
+
+    let mut iter = sequence[1..].iter().map(|byte| Ok(*byte));
+    match event::parse_event(sequence[0], &mut iter) {
+        Ok(event::Event::Key(key)) => Some(Event::Key(key)),
+        Ok(event::Event::Mouse(mouse)) => Some(Event::Mouse(mouse)),
+        _ => {
+            debug!("Unsupported input {:?}", sequence);
+            None
+        }
+    }
+}
+
+/// Reads the events from stdin without blocking, so the shell can do other work while waiting.
+/// Nothing is read while a child runs, so its input stays untouched.
+pub struct Input {
+    bytes: Vec<u8>,
+    events: VecDeque<Event>,
+    eof: bool,
+    resize: Option<RawFd>,
+    /// Whether a cursor position request waits for its answer
+    cursor_pos: bool,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            events: VecDeque::new(),
+            eof: false,
+            resize: None,
+            cursor_pos: false,
+        }
+    }
+
+    /// Replays `bytes` as if they were typed and ends there, e.g. to script the shell in tests.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut input = Self::new();
+        input.bytes.extend_from_slice(bytes);
+        input.eof = true;
+
+        input
+    }
+
+    /// Turns the notifications on `fd` into `Event::Resize`, see `signal::watch_resize`.
+    pub fn watch_resize(&mut self, fd: RawFd) {
+        self.resize = Some(fd);
+    }
+
+    /// Empties the resize pipe. Several resizes in a row only need one repaint.
+    fn drain_resize(&mut self, fd: RawFd) {
+        let mut buffer = [0u8; 64];
+        loop {
+            let read =
+                unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+            if read <= 0 {
+                break;
+            }
+        }
+
+        if !self.events.contains(&Event::Resize) {
+            self.events.push_back(Event::Resize);
+        }
+    }
+
+    /// Whether stdin is closed and every event was handed out.
+    pub fn is_eof(&self) -> bool {
+        self.eof && self.events.is_empty() && self.bytes.is_empty()
+    }
+
+    /// Waits up to `timeout` for stdin or a resize to become readable and reads what has arrived.
+    fn fill(&mut self, timeout: Duration) {
+        if self.eof {
+            return;
+        }
+
+        let poll = |fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let mut fds = [poll(libc::STDIN_FILENO), poll(self.resize.unwrap_or(-1))];
+
+        let millis = timeout.as_millis().min(i32::MAX as u128) as libc::c_int;
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, millis) } <= 0 {
+            return;
+        }
+
+        if let (Some(fd), true) = (self.resize, fds[1].revents & libc::POLLIN != 0) {
+            self.drain_resize(fd);
+        }
+        if fds[0].revents == 0 {
+            return;
+        }
+
+        let mut buffer = [0u8; 4096];
+        let read = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+            )
+        };
+
+        match read {
+            0 => self.eof = true,
+            read if read > 0 => self.bytes.extend_from_slice(&buffer[..read as usize]),
+            _ => {}
+        }
+    }
+
+    /// Parses the sequence at the start of the bytes read and returns whether there was a
+    /// complete one. Sequences are parsed one at a time, so each is read in the state the shell
+    /// is in when it gets there, e.g. after it requested the cursor position.
+    fn parse_next(&mut self) -> bool {
+        let len = match sequence_len(&self.bytes) {
+            Some(len) => len,
+            None => return false,
+        };
+
+        if self.bytes.starts_with(PASTE_START) {
+            match parse_paste(&self.bytes) {
+                Some((text, len)) => {
+                    self.events.push_back(Event::Paste(text));
+                    self.bytes.drain(..len);
+                    return true;
+                }
+                // A long paste arrives in several reads
+                None => return false,
+            }
+        }
+
+        if let Some(event) = parse_sequence(&self.bytes[..len], self.cursor_pos) {
+            self.events.push_back(event);
+        }
+        self.bytes.drain(..len);
+
+        true
+    }
+
+    pub fn next(&mut self, timeout: Duration) -> Option<Event> {
+        while self.events.is_empty() && self.parse_next() {}
+        if self.events.is_empty() {
+            self.fill(timeout);
+            while self.events.is_empty() && self.parse_next() {}
+        }
+
+        // The rest of a sequence never arrives once stdin is closed
+        if self.eof && self.events.is_empty() {
+            self.bytes.clear();
+        }
+
+        self.events.pop_front()
+    }
+
+    /// Waits for the answer to a cursor position request. Other events stay queued.
+    pub fn wait_for_cursor_pos(&mut self, timeout: Duration) -> Option<(u16, u16)> {
+        self.cursor_pos = true;
+        let answer = self.wait_for(timeout, |event| matches!(event, Event::CursorPos(..)));
+        self.cursor_pos = false;
+
+        match answer? {
+            Event::CursorPos(x, y) => Some((x, y)),
+            _ => None,
+        }
+    }
+
+    /// Waits for the answer to a clipboard request. Other events stay queued.
+    pub fn wait_for_clipboard(&mut self, timeout: Duration) -> Option<String> {
+        match self.wait_for(timeout, |event| matches!(event, Event::Clipboard(..)))? {
+            Event::Clipboard(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn wait_for(&mut self, timeout: Duration, answer: fn(&Event) -> bool) -> Option<Event> {
+        let start = Instant::now();
+        loop {
+            if let Some(index) = self.events.iter().position(answer) {
+                return self.events.remove(index);
+            }
+
+            if self.parse_next() {
+                continue;
+            }
+
+            let elapsed = start.elapsed();
+            if elapsed >= timeout || self.eof {
+                return None;
+            }
+
+            self.fill(timeout - elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_paste_is_one_event() {
+        let mut input = Input::from_bytes(b"a\x1b[200~b\r\nc\rd\x1b[201~e");
+
+        assert_eq!(input.next(Duration::ZERO), Some(Event::Key(Key::Char('a'))));
+        assert_eq!(
+            input.next(Duration::ZERO),
+            Some(Event::Paste(String::from("b\nc\nd")))
+        );
+        assert_eq!(input.next(Duration::ZERO), Some(Event::Key(Key::Char('e'))));
+        assert!(input.is_eof());
+    }
+
+    #[test]
+    fn a_paste_waits_for_its_end() {
+        let mut input = Input::new();
+        input.bytes.extend_from_slice(b"\x1b[200~ab");
+        assert!(!input.parse_next());
+        assert!(input.events.is_empty());
+
+        input.bytes.extend_from_slice(b"c\x1b[201~");
+        assert!(input.parse_next());
+        assert_eq!(
+            input.events.pop_front(),
+            Some(Event::Paste(String::from("abc")))
+        );
+    }
+
+    #[test]
+    fn only_an_awaited_cursor_position_is_an_answer() {
+        let mut input = Input::from_bytes(b"\x1b[1;2Ra\x1b[3;7R");
+
+        assert_eq!(input.next(Duration::ZERO), Some(Event::Key(Key::F(3))));
+        assert_eq!(input.wait_for_cursor_pos(Duration::ZERO), Some((7, 3)));
+        assert_eq!(input.next(Duration::ZERO), Some(Event::Key(Key::Char('a'))));
+        assert!(input.is_eof());
+    }
+}
@@ -0,0 +1,424 @@
+use crate::config;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fmt;
+use termion::event::Key;
+
+/// The editor actions keys can be bound to, named like the ones of readline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    AcceptLine,
+    BackwardChar,
+    ForwardChar,
+    BackwardWord,
+    ForwardWord,
+    BeginningOfLine,
+    EndOfLine,
+    BackwardDeleteChar,
+    DeleteChar,
+    UnixWordRubout,
+    KillWord,
+    KillLine,
+    UnixLineDiscard,
+    Yank,
+    YankPop,
+    KillRegion,
+    CopyRegionAsKill,
+    TransposeChars,
+    PreviousHistory,
+    NextHistory,
+    Undo,
+    Redo,
+    ClipboardCopy,
+    ClipboardCopyKill,
+    ClipboardPaste,
+    EditCommandLine,
+}
+
+const ACTIONS: [(&str, Action); 26] = [
+    ("accept-line", Action::AcceptLine),
+    ("backward-char", Action::BackwardChar),
+    ("forward-char", Action::ForwardChar),
+    ("backward-word", Action::BackwardWord),
+    ("forward-word", Action::ForwardWord),
+    ("beginning-of-line", Action::BeginningOfLine),
+    ("end-of-line", Action::EndOfLine),
+    ("backward-delete-char", Action::BackwardDeleteChar),
+    ("delete-char", Action::DeleteChar),
+    ("unix-word-rubout", Action::UnixWordRubout),
+    ("kill-word", Action::KillWord),
+    ("kill-line", Action::KillLine),
+    ("unix-line-discard", Action::UnixLineDiscard),
+    ("yank", Action::Yank),
+    ("yank-pop", Action::YankPop),
+    ("kill-region", Action::KillRegion),
+    ("copy-region-as-kill", Action::CopyRegionAsKill),
+    ("transpose-chars", Action::TransposeChars),
+    ("previous-history", Action::PreviousHistory),
+    ("next-history", Action::NextHistory),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("clipboard-copy", Action::ClipboardCopy),
+    ("clipboard-copy-kill", Action::ClipboardCopyKill),
+    ("clipboard-paste", Action::ClipboardPaste),
+    ("edit-command-line", Action::EditCommandLine),
+];
+
+const DEFAULT_BINDINGS: [(&str, Action); 31] = [
+    ("enter", Action::AcceptLine),
+    ("backspace", Action::BackwardDeleteChar),
+    ("delete", Action::DeleteChar),
+    ("ctrl-a", Action::BeginningOfLine),
+    ("home", Action::BeginningOfLine),
+    ("ctrl-e", Action::EndOfLine),
+    ("end", Action::EndOfLine),
+    ("ctrl-b", Action::BackwardChar),
+    ("left", Action::BackwardChar),
+    ("ctrl-f", Action::ForwardChar),
+    ("right", Action::ForwardChar),
+    ("alt-b", Action::BackwardWord),
+    ("alt-f", Action::ForwardWord),
+    ("ctrl-w", Action::UnixWordRubout),
+    ("alt-d", Action::KillWord),
+    ("ctrl-k", Action::KillLine),
+    ("ctrl-u", Action::UnixLineDiscard),
+    ("ctrl-y", Action::Yank),
+    ("alt-y", Action::YankPop),
+    ("ctrl-x ctrl-w", Action::KillRegion),
+    ("alt-w", Action::CopyRegionAsKill),
+    ("ctrl-t", Action::TransposeChars),
+    ("up", Action::PreviousHistory),
+    ("down", Action::NextHistory),
+    ("ctrl-_", Action::Undo),
+    ("ctrl-x ctrl-u", Action::Undo),
+    ("alt-_", Action::Redo),
+    ("ctrl-x w", Action::ClipboardCopy),
+    ("ctrl-x k", Action::ClipboardCopyKill),
+    ("ctrl-x y", Action::ClipboardPaste),
+    ("ctrl-x ctrl-e", Action::EditCommandLine),
+];
+
+/// Terminals send ctrl with these as the control characters termion reads as ctrl-4 to ctrl-7.
+const CONTROL_SYMBOLS: [(char, char); 4] = [('\\', '4'), (']', '5'), ('^', '6'), ('_', '7')];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(action, _)| *action == name)
+            .map(|(_, action)| *action)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(_, action)| action == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        ACTIONS.iter().map(|(name, _)| *name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    Action(Action),
+    /// A command run like a typed one, while the line is kept
+    Command(String),
+}
+
+impl Binding {
+    pub fn from_config(binding: &config::Binding) -> Result<Self, String> {
+        match binding {
+            config::Binding::Action(name) => Action::from_name(name)
+                .map(Binding::Action)
+                .ok_or_else(|| format!("unknown action {}", name)),
+            config::Binding::Command { command } => Ok(Binding::Command(command.to_owned())),
+        }
+    }
+}
+
+/// Shown like the arguments of the `bind` builtin.
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Action(action) => f.write_str(action.name()),
+            Binding::Command(command) => write!(f, "-- {}", command),
+        }
+    }
+}
+
+fn parse_key(name: &str) -> Result<Key, String> {
+    let lower = name.to_lowercase();
+    let single = |rest: &str| {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Ok(ch),
+            _ => Err(format!("invalid key {}", name)),
+        }
+    };
+
+    let key = if let Some(rest) = lower.strip_prefix("ctrl-") {
+        let ch = single(rest)?;
+        let control = CONTROL_SYMBOLS.iter().find(|(symbol, _)| *symbol == ch);
+        Key::Ctrl(control.map_or(ch, |(_, digit)| *digit))
+    } else if let Some(rest) = name.get(4..).filter(|_| lower.starts_with("alt-")) {
+        Key::Alt(single(rest)?)
+    } else {
+        match lower.as_str() {
+            "enter" => Key::Char('\n'),
+            "tab" => Key::Char('\t'),
+            "space" => Key::Char(' '),
+            "esc" => Key::Esc,
+            "backspace" => Key::Backspace,
+            "delete" => Key::Delete,
+            "insert" => Key::Insert,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "pageup" => Key::PageUp,
+            "pagedown" => Key::PageDown,
+            function if function.starts_with('f') && function.len() > 1 => function[1..]
+                .parse()
+                .map(Key::F)
+                .map_err(|_| format!("invalid key {}", name))?,
+            _ => Key::Char(single(name)?),
+        }
+    };
+
+    Ok(key)
+}
+
+/// Parses a sequence of keys separated by spaces, like "ctrl-x ctrl-e".
+pub fn parse_keys(text: &str) -> Result<Vec<Key>, String> {
+    let keys = text
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<Key>, String>>()?;
+
+    if keys.is_empty() {
+        Err(String::from("no keys given"))
+    } else {
+        Ok(keys)
+    }
+}
+
+fn key_name(key: &Key) -> String {
+    match key {
+        Key::Ctrl(ch) => {
+            let control = CONTROL_SYMBOLS.iter().find(|(_, digit)| digit == ch);
+            format!("ctrl-{}", control.map_or(*ch, |(symbol, _)| *symbol))
+        }
+        Key::Alt(ch) => format!("alt-{}", ch),
+        Key::Char('\n') => String::from("enter"),
+        Key::Char('\t') => String::from("tab"),
+        Key::Char(' ') => String::from("space"),
+        Key::Char(ch) => ch.to_string(),
+        Key::F(number) => format!("f{}", number),
+        key => format!("{:?}", key).to_lowercase(),
+    }
+}
+
+/// The names of `keys` in the form `parse_keys` reads.
+pub fn key_names(keys: &[Key]) -> String {
+    keys.iter().map(key_name).collect::<Vec<String>>().join(" ")
+}
+
+pub enum Dispatch {
+    /// The keys so far start a longer sequence
+    Pending,
+    Bound(Binding),
+    /// The keys are not bound, a single printable key inserts itself
+    Unbound(Vec<Key>),
+}
+
+/// Maps keys and sequences of keys to their bindings. A sequence which starts a longer one waits
+/// for the next key, so it is only reached once the longer one is removed.
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Binding)>,
+    /// The keys of a sequence typed so far, like the `ctrl-x` of `ctrl-x ctrl-e`
+    pending: Vec<Key>,
+}
+
+impl Keymap {
+    /// The default bindings with the ones of the config on top.
+    pub fn new(config: &HashMap<String, config::Binding>) -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            pending: Vec::new(),
+        };
+
+        for (keys, action) in DEFAULT_BINDINGS.iter() {
+            let keys = parse_keys(keys).expect("Invalid default binding");
+            keymap.bind(keys, Binding::Action(*action));
+        }
+
+        let mut configured: Vec<_> = config.iter().collect();
+        configured.sort_by_key(|(keys, _)| keys.as_str());
+        for (keys, binding) in configured {
+            match (parse_keys(keys), Binding::from_config(binding)) {
+                (Ok(keys), Ok(binding)) => keymap.bind(keys, binding),
+                (Err(e), _) | (_, Err(e)) => warn!("Invalid binding {:?}: {}", keys, e),
+            }
+        }
+
+        keymap
+    }
+
+    pub fn bindings(&self) -> &[(Vec<Key>, Binding)] {
+        &self.bindings
+    }
+
+    /// Binds `keys`, replacing what they were bound to.
+    pub fn bind(&mut self, keys: Vec<Key>, binding: Binding) {
+        debug!("Bind {} to {}", key_names(&keys), binding);
+
+        match self.bindings.iter_mut().find(|(bound, _)| *bound == keys) {
+            Some((_, bound)) => *bound = binding,
+            None => self.bindings.push((keys, binding)),
+        }
+    }
+
+    /// Removes the binding of `keys` and returns whether there was one.
+    pub fn unbind(&mut self, keys: &[Key]) -> bool {
+        let len = self.bindings.len();
+        self.bindings.retain(|(bound, _)| bound != keys);
+
+        self.bindings.len() < len
+    }
+
+    /// Whether the keys typed so far start a longer sequence.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Looks up `key` following the keys typed before it.
+    pub fn dispatch(&mut self, key: Key) -> Dispatch {
+        self.pending.push(key);
+
+        let pending = &self.pending;
+        let starts_longer = self
+            .bindings
+            .iter()
+            .any(|(keys, _)| keys.len() > pending.len() && keys.starts_with(pending));
+        if starts_longer {
+            return Dispatch::Pending;
+        }
+
+        let keys = std::mem::take(&mut self.pending);
+        match self.bindings.iter().find(|(bound, _)| *bound == keys) {
+            Some((_, binding)) => Dispatch::Bound(binding.clone()),
+            None => Dispatch::Unbound(keys),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(config: &str) -> Keymap {
+        Keymap::new(&toml::from_str(config).unwrap())
+    }
+
+    fn dispatch(keymap: &mut Keymap, keys: &str) -> Option<Binding> {
+        let keys = parse_keys(keys).unwrap();
+        let (last, first) = keys.split_last().unwrap();
+        for key in first {
+            assert!(matches!(keymap.dispatch(*key), Dispatch::Pending));
+        }
+
+        match keymap.dispatch(*last) {
+            Dispatch::Bound(binding) => Some(binding),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn keys_are_parsed_by_name() {
+        assert_eq!(
+            parse_keys("ctrl-x ctrl-E alt-. alt-B enter f5 q"),
+            Ok(vec![
+                Key::Ctrl('x'),
+                Key::Ctrl('e'),
+                Key::Alt('.'),
+                Key::Alt('B'),
+                Key::Char('\n'),
+                Key::F(5),
+                Key::Char('q'),
+            ])
+        );
+        assert!(parse_keys("ctrl-xy").is_err());
+        assert!(parse_keys("hyper-x").is_err());
+        assert!(parse_keys(" ").is_err());
+
+        assert_eq!(parse_keys("ctrl-_"), Ok(vec![Key::Ctrl('7')]));
+
+        let keys = "ctrl-x alt-. space pageup f12 ctrl-_";
+        assert_eq!(key_names(&parse_keys(keys).unwrap()), keys);
+    }
+
+    #[test]
+    fn the_config_adds_and_replaces_bindings() {
+        let mut keymap = keymap(
+            r#"
+            "ctrl-a" = "end-of-line"
+            "alt-." = { command = "ls -la" }
+            "ctrl-x ctrl-e" = "kill-line"
+            "ctrl-q" = "no-such-action"
+            "#,
+        );
+
+        assert_eq!(
+            dispatch(&mut keymap, "ctrl-a"),
+            Some(Binding::Action(Action::EndOfLine))
+        );
+        assert_eq!(
+            dispatch(&mut keymap, "alt-."),
+            Some(Binding::Command(String::from("ls -la")))
+        );
+        assert_eq!(
+            dispatch(&mut keymap, "ctrl-x ctrl-e"),
+            Some(Binding::Action(Action::KillLine))
+        );
+        assert_eq!(dispatch(&mut keymap, "ctrl-q"), None);
+        assert_eq!(
+            dispatch(&mut keymap, "ctrl-e"),
+            Some(Binding::Action(Action::EndOfLine))
+        );
+    }
+
+    #[test]
+    fn an_unknown_sequence_is_dropped() {
+        let mut keymap = keymap("");
+
+        assert!(matches!(keymap.dispatch(Key::Ctrl('x')), Dispatch::Pending));
+        match keymap.dispatch(Key::Char('a')) {
+            Dispatch::Unbound(keys) => assert_eq!(keys, [Key::Ctrl('x'), Key::Char('a')]),
+            _ => panic!("ctrl-x a is not bound"),
+        }
+        assert!(matches!(
+            keymap.dispatch(Key::Char('a')),
+            Dispatch::Unbound(_)
+        ));
+
+        let prefixed: Vec<Vec<Key>> = keymap
+            .bindings()
+            .iter()
+            .map(|(keys, _)| keys.clone())
+            .filter(|keys| keys.len() > 1 && keys[0] == Key::Ctrl('x'))
+            .collect();
+        for keys in prefixed {
+            assert!(keymap.unbind(&keys));
+        }
+        assert!(matches!(
+            keymap.dispatch(Key::Ctrl('x')),
+            Dispatch::Unbound(_)
+        ));
+    }
+}
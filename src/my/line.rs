@@ -1,38 +1,103 @@
 use crate::behaviour::Behaviour;
 use crate::drawable::Drawable;
 use crate::shell::line::XCursor;
-use crate::{config, shell};
+use crate::{config, shell, text};
 use log::debug;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ops::Range;
+use termion::color::{Color, Rgb};
 
+/// The number of kills the kill ring keeps
+const KILL_RING_SIZE: usize = 32;
+
+/// Points at a grapheme cluster of the line, since that is what the user sees as a character.
 struct MyXCursor {
-    index: u16,
+    index: usize,
+    len: usize,
 }
 
 impl MyXCursor {
     fn new() -> Self {
         debug!("Create new XCursor with x = 0");
-        Self { index: 0 }
+        Self { index: 0, len: 0 }
     }
 }
 
 impl XCursor for MyXCursor {
     fn move_left(&mut self) {
-        self.index -= 1;
+        if self.index > 0 {
+            self.index -= 1;
+        }
         debug!("Move XCursor to the left. x = {}", self.index);
     }
 
     fn move_right(&mut self) {
-        self.index += 1;
+        if self.index < self.len {
+            self.index += 1;
+        }
         debug!("Move XCursor to the right. x = {}", self.index);
     }
 }
 
+/// Whether a grapheme cluster belongs to a word for the word motions, like letters and digits.
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
+fn is_not_whitespace(grapheme: &str) -> bool {
+    !grapheme.trim().is_empty()
+}
+
+/// Returns the index the word in front of `index` starts at, skipping what is no word first.
+fn word_start(graphemes: &[&str], index: usize, is_word: fn(&str) -> bool) -> usize {
+    let mut index = index;
+    while index > 0 && !is_word(graphemes[index - 1]) {
+        index -= 1;
+    }
+    while index > 0 && is_word(graphemes[index - 1]) {
+        index -= 1;
+    }
+
+    index
+}
+
+/// Returns the index behind the word at `index`, skipping what is no word first.
+fn word_end(graphemes: &[&str], index: usize, is_word: fn(&str) -> bool) -> usize {
+    let mut index = index;
+    while index < graphemes.len() && !is_word(graphemes[index]) {
+        index += 1;
+    }
+    while index < graphemes.len() && is_word(graphemes[index]) {
+        index += 1;
+    }
+
+    index
+}
+
 pub struct Line {
-    input: Vec<char>,
+    input: String,
     config: config::Line,
     xcursor: MyXCursor,
-    behaviour: Vec<Box<Behaviour>>,
-    padding: u8,
+    behaviour: Vec<Box<dyn Behaviour>>,
+    padding: u16,
+    /// The killed texts for `yank`, the last one first
+    kill_ring: VecDeque<String>,
+    /// The range of the last yank and its entry of the kill ring, until the next change
+    yanked: Option<(Range<usize>, usize)>,
+    /// The text and cursor before every change, for `undo`
+    undo: Vec<(String, usize)>,
+    /// The changes undone, until the next change
+    redo: Vec<(String, usize)>,
+    /// Whether the changes are grouped into one, which saved the state before it already
+    grouped: bool,
+    /// Where the last typed character ended, the next one there belongs to the same change
+    typed_to: Option<usize>,
+    /// The columns of the last rendering, which decide where the rows wrap
+    columns: Cell<usize>,
+    /// Where the selection starts, which ends at the cursor
+    mark: Option<usize>,
+    selection_color: Option<Rgb>,
 }
 
 impl Line {
@@ -40,47 +105,296 @@ impl Line {
         debug!("New Line: {:?}", config);
 
         Self {
-            input: Vec::with_capacity(config.capacity as usize),
+            input: String::with_capacity(config.capacity as usize),
             config: config.clone(),
             xcursor: MyXCursor::new(),
             behaviour: Vec::new(),
             padding: 0,
+            kill_ring: VecDeque::new(),
+            yanked: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            grouped: false,
+            typed_to: None,
+            columns: Cell::new(usize::MAX),
+            mark: None,
+            selection_color: None,
         }
     }
 
-    pub fn add_behaviour(&mut self, behaviour: Box<Behaviour>) {
+    pub fn add_behaviour(&mut self, behaviour: Box<dyn Behaviour>) {
         self.behaviour.push(behaviour);
     }
+
+    /// Draws the selection in `color` over the colors of the behaviours.
+    pub fn set_selection_color(&mut self, color: Option<Rgb>) {
+        self.selection_color = color;
+    }
+
+    fn start(&self) -> u16 {
+        self.padding + u16::from(self.config.left_padding)
+    }
+
+    /// Returns the column and row in front of every grapheme cluster and behind the last one,
+    /// with the rows counted from the first row of the line.
+    fn layout(&self, columns: usize) -> Vec<(usize, usize)> {
+        let mut positions = Vec::with_capacity(self.xcursor.len + 1);
+        let (mut x, mut row) = (usize::from(self.start()), 0);
+        for grapheme in text::graphemes(&self.input) {
+            if grapheme == "\n" {
+                positions.push((x, row));
+                x = 0;
+                row += 1;
+                continue;
+            }
+
+            // Wraps like `text::advance`
+            let width = text::grapheme_width(grapheme);
+            if x + width > columns {
+                x = 0;
+                row += 1;
+            }
+
+            positions.push((x, row));
+            x += width;
+            if x >= columns {
+                x = 0;
+                row += 1;
+            }
+        }
+        positions.push((x, row));
+
+        positions
+    }
+
+    /// Returns the grapheme cluster at column `x` of `row`, counted from the first row of the
+    /// line, or the end of the row when `x` is behind it.
+    pub fn index_at(&self, x: usize, row: usize) -> Option<usize> {
+        Self::index_on(&self.layout(self.columns.get()), x, row)
+    }
+
+    fn index_on(layout: &[(usize, usize)], x: usize, row: usize) -> Option<usize> {
+        let on_row = || layout.iter().enumerate().filter(|(_, (_, on))| *on == row);
+
+        on_row()
+            .rfind(|(_, (column, _))| *column <= x)
+            .or_else(|| on_row().next())
+            .map(|(index, _)| index)
+    }
+
+    /// Moves the cursor one row up or down to the column closest to the current one.
+    fn move_row(&mut self, up: bool) -> bool {
+        let layout = self.layout(self.columns.get());
+        let (x, row) = layout[self.xcursor.index];
+        let target = match (up, row) {
+            (true, 0) => return false,
+            (true, row) => row - 1,
+            (false, row) => row + 1,
+        };
+
+        match Self::index_on(&layout, x, target) {
+            Some(index) => {
+                self.xcursor.index = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn offset(&self, index: usize) -> usize {
+        text::byte_offset(&self.input, index)
+    }
+
+    /// Remembers the text before a change, unless it belongs to an open group.
+    fn save(&mut self) {
+        if !self.grouped {
+            self.undo.push((self.input.clone(), self.xcursor.index));
+        }
+
+        self.redo.clear();
+        self.yanked = None;
+        self.typed_to = None;
+    }
+
+    /// Goes back to a state of the undo or redo list and keeps the current one on the other.
+    fn restore(&mut self, (input, index): (String, usize), undoing: bool) {
+        debug!("Restore {:?}", input);
+
+        let state = (
+            std::mem::replace(&mut self.input, input),
+            self.xcursor.index,
+        );
+        if undoing {
+            self.redo.push(state);
+        } else {
+            self.undo.push(state);
+        }
+
+        self.xcursor.len = text::graphemes(&self.input).count();
+        self.xcursor.index = index.min(self.xcursor.len);
+        self.yanked = None;
+        self.typed_to = None;
+    }
+
+    /// Replaces the grapheme clusters in `range` with `text` and puts the cursor behind it,
+    /// without saving for `undo`.
+    fn splice(&mut self, range: Range<usize>, text: &str) {
+        let bytes = self.offset(range.start)..self.offset(range.end);
+        let behind = bytes.start + text.len();
+        self.input.replace_range(bytes, text);
+
+        self.xcursor.len = text::graphemes(&self.input).count();
+        self.xcursor.index = text::graphemes(&self.input[..behind]).count();
+    }
+
+    /// Removes the grapheme cluster at `index`.
+    fn remove(&mut self, index: usize) {
+        let range = self.offset(index)..self.offset(index + 1);
+        debug!("Remove {:?} from Line", &self.input[range.clone()]);
+
+        self.save();
+        self.input.replace_range(range, "");
+        self.xcursor.len = text::graphemes(&self.input).count();
+    }
+
+    pub fn graphemes(&self) -> Vec<&str> {
+        text::graphemes(&self.input).collect()
+    }
+
+    /// The grapheme cluster the cursor is in front of.
+    pub fn index(&self) -> usize {
+        self.xcursor.index
+    }
+
+    pub fn set_index(&mut self, index: usize) {
+        self.xcursor.index = index.min(self.xcursor.len);
+    }
+
+    /// The newest entry of the kill ring.
+    pub fn killed(&self) -> Option<&str> {
+        self.kill_ring.front().map(String::as_str)
+    }
+
+    /// Starts a selection at `index`, or ends it with `None`.
+    pub fn set_mark(&mut self, index: Option<usize>) {
+        self.mark = index;
+    }
+
+    /// The grapheme clusters between the mark and the cursor, if any.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let mark = self.mark?.min(self.xcursor.len);
+        let index = self.xcursor.index;
+        let range = mark.min(index)..mark.max(index);
+
+        if range.is_empty() {
+            None
+        } else {
+            Some(range)
+        }
+    }
+
+    /// Replaces the grapheme clusters in `range` with `text` and leaves the cursor in front of it.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) {
+        let bytes = self.offset(range.start)..self.offset(range.end);
+        debug!(
+            "Replace {:?} with {:?} in Line",
+            &self.input[bytes.clone()],
+            text
+        );
+
+        self.save();
+        self.splice(range.clone(), text);
+        self.xcursor.index = range.start.min(self.xcursor.len);
+    }
+
+    /// Puts the grapheme clusters in `range` on the kill ring, without removing them.
+    pub fn copy(&mut self, range: Range<usize>) {
+        let bytes = self.offset(range.start)..self.offset(range.end);
+        if bytes.is_empty() {
+            return;
+        }
+
+        self.kill_ring.push_front(self.input[bytes].to_owned());
+        self.kill_ring.truncate(KILL_RING_SIZE);
+    }
+
+    /// Removes the grapheme clusters in `range` and puts them on the kill ring.
+    pub fn kill(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        self.copy(range.clone());
+        debug!("Kill {:?} from Line", self.kill_ring.front());
+        self.replace(range, "");
+    }
+
+    /// Inserts `text` at the cursor and moves the cursor behind it.
+    pub fn insert_text(&mut self, text: &str) {
+        let index = self.xcursor.index;
+        self.save();
+        self.splice(index..index, text);
+    }
+
+    /// Makes the following changes one for `undo`, until `end_group`.
+    pub fn begin_group(&mut self) {
+        self.save();
+        self.grouped = true;
+    }
+
+    /// Closes the group, a group without changes leaves nothing to undo.
+    pub fn end_group(&mut self) {
+        if self.grouped {
+            self.grouped = false;
+            if self.undo.last().map(|(input, _)| input) == Some(&self.input) {
+                self.undo.pop();
+            }
+        }
+    }
 }
 
 impl shell::Line for Line {
     fn remove_after(&mut self) {
         if self.xcursor.index > 0 {
-            let index = (self.xcursor.index - 1) as usize;
+            let index = self.xcursor.index - 1;
             debug!("Remove after: {}", index);
 
-            self.input.remove(index);
+            self.remove(index);
             self.xcursor.move_left();
         }
     }
 
     fn remove_before(&mut self) {
-        let index = self.xcursor.index as usize;
+        let index = self.xcursor.index;
         debug!("Remove before: {}", index);
 
-        self.input.remove(index);
+        if index < self.xcursor.len {
+            self.remove(index);
+        }
     }
 
-    fn cursor(&mut self) -> &mut XCursor {
+    fn cursor(&mut self) -> &mut dyn XCursor {
         &mut self.xcursor
     }
 
     fn insert(&mut self, ch: char) {
-        let index = self.xcursor.index as usize;
+        let index = self.xcursor.index;
         debug!("Insert {} into Line at index {}", ch, index);
 
-        self.input.insert(index, ch);
-        self.xcursor.move_right();
+        // Typing on is one change for `undo`
+        if self.typed_to != Some(index) {
+            self.save();
+        }
+
+        // A combining character joins the grapheme cluster in front of it instead of adding one
+        let offset = self.offset(index);
+        self.input.insert(offset, ch);
+
+        let len = text::graphemes(&self.input).count();
+        let added = len.saturating_sub(self.xcursor.len);
+        self.xcursor.len = len;
+        self.xcursor.index = (index + added).min(len);
+        self.typed_to = Some(self.xcursor.index);
     }
 
     fn reset(&mut self) {
@@ -88,30 +402,369 @@ impl shell::Line for Line {
 
         self.input.clear();
         self.xcursor = MyXCursor::new();
+        self.undo.clear();
+        self.redo.clear();
+        self.yanked = None;
+        self.grouped = false;
+        self.typed_to = None;
+        self.mark = None;
+    }
+
+    fn set_text(&mut self, text: &str) {
+        debug!("Set Line to {}", text);
+
+        self.save();
+        self.input.clear();
+        self.input.push_str(text);
+        self.xcursor.len = text::graphemes(&self.input).count();
+        self.xcursor.index = self.xcursor.len;
+    }
+
+    fn move_up(&mut self) -> bool {
+        self.move_row(true)
+    }
+
+    fn move_down(&mut self) -> bool {
+        self.move_row(false)
+    }
+
+    fn move_to_start(&mut self) {
+        self.xcursor.index = 0;
+    }
+
+    fn move_to_end(&mut self) {
+        self.xcursor.index = self.xcursor.len;
+    }
+
+    fn move_word_left(&mut self) {
+        self.xcursor.index = word_start(&self.graphemes(), self.xcursor.index, is_word);
+    }
+
+    fn move_word_right(&mut self) {
+        self.xcursor.index = word_end(&self.graphemes(), self.xcursor.index, is_word);
+    }
+
+    fn kill_word_before(&mut self) {
+        let index = self.xcursor.index;
+        let start = word_start(&self.graphemes(), index, is_not_whitespace);
+        self.kill(start..index);
+    }
+
+    fn kill_word_after(&mut self) {
+        let index = self.xcursor.index;
+        let end = word_end(&self.graphemes(), index, is_word);
+        self.kill(index..end);
+    }
+
+    fn kill_to_end(&mut self) {
+        self.kill(self.xcursor.index..self.xcursor.len);
+    }
+
+    fn kill_to_start(&mut self) {
+        self.kill(0..self.xcursor.index);
+    }
+
+    fn yank(&mut self) {
+        let killed = match self.kill_ring.front() {
+            Some(killed) => killed.clone(),
+            None => return,
+        };
+        debug!("Yank {:?} into Line", killed);
+
+        let start = self.xcursor.index;
+        self.insert_text(&killed);
+        self.yanked = Some((start..self.xcursor.index, 0));
+    }
+
+    fn yank_pop(&mut self) {
+        let (range, entry) = match self.yanked.clone() {
+            Some((range, entry)) if range.end == self.xcursor.index => (range, entry),
+            _ => return,
+        };
+
+        let entry = (entry + 1) % self.kill_ring.len();
+        let killed = self.kill_ring[entry].clone();
+        debug!("Replace the yank with {:?}", killed);
+
+        self.save();
+        self.splice(range.clone(), &killed);
+        self.yanked = Some((range.start..self.xcursor.index, entry));
+    }
+
+    fn transpose(&mut self) {
+        let index = self.xcursor.index.min(self.xcursor.len.saturating_sub(1));
+        if index == 0 {
+            return;
+        }
+
+        let graphemes = self.graphemes();
+        let swapped = format!("{}{}", graphemes[index], graphemes[index - 1]);
+        let range = self.offset(index - 1)..self.offset(index + 1);
+        debug!("Transpose {:?} in Line", &self.input[range.clone()]);
+
+        self.save();
+        self.input.replace_range(range, &swapped);
+        self.xcursor.len = text::graphemes(&self.input).count();
+        self.xcursor.index = (index + 1).min(self.xcursor.len);
     }
 
-    fn set_padding(&mut self, cursor: &shell::Cursor) {
+    fn undo(&mut self) {
+        if let Some(state) = self.undo.pop() {
+            self.restore(state, true);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.redo.pop() {
+            self.restore(state, false);
+        }
+    }
+
+    fn text(&self) -> String {
+        self.input.clone()
+    }
+
+    fn set_padding(&mut self, cursor: &dyn shell::Cursor) {
         let (x, _) = cursor.get();
-        self.padding = x as u8;
+        self.padding = x;
 
         debug!("Set padding to {}", x);
     }
+
+    /// The end of the first row of a line with line breaks.
+    fn end(&self) -> u16 {
+        let first = self.input.split('\n').next().unwrap_or_default();
+        self.start() + text::width(first) as u16
+    }
 }
 
 impl Drawable for Line {
-    fn render_on(&self, term: &mut shell::Terminal) {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
         debug!("Draw the line");
 
-        let x = u16::from(self.padding + self.config.left_padding);
-        term.cursor().set_x(x).clear_after(); // Clean line after Prompt + Padding
+        let (columns, _) = term.size();
+        let columns = usize::from(columns).max(1);
+        self.columns.set(columns);
+
+        // Clean every row of the line, starting behind Prompt + Padding
+        let (_, y) = term.cursor().get();
+        term.cursor().set_to(self.start(), y).clear_after();
 
         for behaviour in self.behaviour.iter() {
             behaviour.render(&self.input, term);
         }
 
-        let len = self.input.len() as u16;
-        if self.xcursor.index < len {
-            term.cursor().move_left(len - self.xcursor.index); // Correct Cursor-Pos after re-draw
+        let layout = self.layout(columns);
+        if let (Some(range), Some(color)) = (self.selection(), self.selection_color.as_ref()) {
+            let (x, row) = layout[range.start];
+            let selected: String = self.graphemes()[range].concat();
+            term.cursor().set_to(x as u16, y + row as u16);
+            term.in_color(Some(color as &dyn Color))
+                .write_text(&selected);
         }
+
+        // Correct Cursor-Pos after re-draw
+        let (x, row) = layout[self.xcursor.index];
+        term.cursor().set_to(x as u16, y + row as u16);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::WordColorizeBehaviour;
+    use crate::mock::MockTerminal;
+    use crate::shell::{Line as _, Terminal};
+
+    fn line() -> Line {
+        let mut line = Line::new(&config::Line {
+            left_padding: 0,
+            ..config::Line::default()
+        });
+        line.add_behaviour(Box::new(WordColorizeBehaviour::new(
+            &config::Colorize::default(),
+        )));
+        line
+    }
+
+    fn type_text(line: &mut Line, text: &str) {
+        text.chars().for_each(|ch| line.insert(ch));
+    }
+
+    /// Draws `line` behind a prompt of `prompt`, like the shell does.
+    fn draw(line: &mut Line, prompt: &str, columns: u16) -> MockTerminal {
+        let mut term = MockTerminal::new(columns, 5);
+        term.in_color(None).write_text(prompt);
+        line.set_padding(term.cursor());
+        line.render_on(&mut term);
+        term
+    }
+
+    #[test]
+    fn combining_characters_join_the_grapheme_in_front() {
+        let mut line = line();
+        type_text(&mut line, "ae\u{301}b");
+        line.cursor().move_left();
+        line.remove_after();
+
+        assert_eq!(line.text(), "ab");
+        assert_eq!(line.end(), 2);
+    }
+
+    #[test]
+    fn word_motions_skip_punctuation() {
+        let mut line = line();
+        type_text(&mut line, "git log --oneline");
+
+        line.move_word_left();
+        line.move_word_left();
+        line.insert('X');
+        assert_eq!(line.text(), "git Xlog --oneline");
+
+        line.move_word_right();
+        line.move_word_right();
+        line.insert('Y');
+        assert_eq!(line.text(), "git Xlog --onelineY");
+    }
+
+    #[test]
+    fn kills_can_be_yanked_back() {
+        let mut line = line();
+        type_text(&mut line, "cat foo/bar.txt");
+
+        line.kill_word_before();
+        assert_eq!(line.text(), "cat ");
+
+        line.move_to_start();
+        line.yank();
+        line.insert(' ');
+        assert_eq!(line.text(), "foo/bar.txt cat ");
+
+        line.move_to_start();
+        line.kill_word_after();
+        assert_eq!(line.text(), "/bar.txt cat ");
+
+        line.kill_to_end();
+        assert_eq!(line.text(), "");
+        line.yank();
+        assert_eq!(line.text(), "/bar.txt cat ");
+
+        line.move_word_left();
+        line.kill_to_start();
+        assert_eq!(line.text(), "cat ");
+    }
+
+    #[test]
+    fn yank_pop_goes_round_the_kill_ring() {
+        let mut line = line();
+        type_text(&mut line, "one two three");
+        line.kill_word_before();
+        line.kill_word_before();
+        line.kill_word_before();
+        assert_eq!(line.text(), "");
+
+        line.yank();
+        assert_eq!(line.text(), "one ");
+        line.yank_pop();
+        assert_eq!(line.text(), "two ");
+        line.yank_pop();
+        assert_eq!(line.text(), "three");
+        line.yank_pop();
+        assert_eq!(line.text(), "one ");
+
+        // Only right after a yank
+        line.insert('!');
+        line.yank_pop();
+        assert_eq!(line.text(), "one !");
+    }
+
+    #[test]
+    fn undo_takes_back_typing_in_one_step() {
+        let mut line = line();
+        type_text(&mut line, "ls -la");
+        line.move_word_left();
+        type_text(&mut line, "--");
+
+        line.undo();
+        assert_eq!(line.text(), "ls -la");
+        line.undo();
+        assert_eq!(line.text(), "");
+        line.undo();
+        assert_eq!(line.text(), "");
+
+        line.redo();
+        assert_eq!(line.text(), "ls -la");
+        line.redo();
+        assert_eq!(line.text(), "ls ---la");
+        assert_eq!(line.index(), 6);
+    }
+
+    #[test]
+    fn a_discarded_line_comes_back() {
+        let mut line = line();
+        type_text(&mut line, "rm -rf build");
+        line.kill_to_start();
+        line.undo();
+        assert_eq!(line.text(), "rm -rf build");
+
+        // A new change drops what was undone
+        line.undo();
+        line.insert('x');
+        line.redo();
+        assert_eq!(line.text(), "x");
+    }
+
+    #[test]
+    fn transpose_swaps_characters() {
+        let mut line = line();
+        type_text(&mut line, "sl");
+
+        line.transpose();
+        assert_eq!(line.text(), "ls");
+
+        line.move_to_start();
+        line.cursor().move_right();
+        type_text(&mut line, "日");
+        line.cursor().move_left();
+        line.transpose();
+        assert_eq!(line.text(), "日ls");
+        assert_eq!(line.end(), 4);
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        let mut line = line();
+        type_text(&mut line, "日本");
+        line.cursor().move_left();
+
+        let term = draw(&mut line, "> ", 20);
+        assert_eq!(term.lines(), ["> 日本"]);
+        assert_eq!(term.cursor_cell(), (4, 0));
+        assert_eq!(line.end(), 6);
+    }
+
+    #[test]
+    fn long_lines_wrap_onto_the_next_rows() {
+        let mut line = line();
+        type_text(&mut line, "abcdefghijkl");
+
+        let term = draw(&mut line, "> ", 10);
+        assert_eq!(term.lines(), ["> abcdefgh", "ijkl"]);
+        assert_eq!(term.cursor_cell(), (4, 1));
+    }
+
+    #[test]
+    fn up_and_down_move_between_the_rows() {
+        let mut line = line();
+        type_text(&mut line, "abcdefghijkl");
+        draw(&mut line, "> ", 10);
+
+        assert!(line.move_up());
+        assert_eq!(draw(&mut line, "> ", 10).cursor_cell(), (4, 0));
+        assert!(!line.move_up());
+
+        assert!(line.move_down());
+        assert_eq!(draw(&mut line, "> ", 10).cursor_cell(), (4, 1));
+        assert!(!line.move_down());
     }
 }
@@ -1,7 +1,18 @@
+pub mod history;
+pub mod input;
+pub mod keymap;
 pub mod line;
+pub mod renderer;
+pub mod screen;
 pub mod shell;
 pub mod terminal;
+pub mod vi;
 
+pub use self::history::History;
+pub use self::keymap::Keymap;
 pub use self::line::Line;
+pub use self::renderer::Renderer;
+pub use self::screen::Screen;
 pub use self::shell::Shell;
-pub use self::terminal::Terminal;
+pub use self::terminal::{CursorShape, Terminal};
+pub use self::vi::Vi;
@@ -0,0 +1,231 @@
+use crate::my::screen::{Cell, Pen, Screen};
+use crate::shell::Terminal;
+use crate::text;
+use log::debug;
+use std::io::{self, Write};
+use termion::color::{self, Color};
+use termion::{clear, cursor};
+
+/// Brings a `Screen` to the terminal by comparing it with the last one and writing only the
+/// cells which changed. The cursor is only moved relative to where it is, so the region can
+/// start anywhere and scroll with the terminal.
+pub struct Renderer {
+    /// What the terminal shows of the region
+    drawn: Vec<Vec<Cell>>,
+    /// The rows of the region which exist on the terminal, further ones are added by newlines
+    rows: usize,
+    /// Column and row of the cursor, with the rows counted from the first row of the region
+    cursor: (usize, usize),
+    pen: Option<Pen>,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self {
+            drawn: Vec::new(),
+            rows: 1,
+            cursor: (0, 0),
+            pen: None,
+        }
+    }
+
+    /// The column and row of the cursor, with the rows counted from the first row of the region.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    fn set_pen(&mut self, pen: &Option<Pen>, out: &mut dyn Write) -> io::Result<()> {
+        if self.pen != *pen {
+            match pen {
+                Some(pen) => write!(out, "{}", color::Fg(pen as &dyn Color))?,
+                None => write!(out, "{}", color::Fg(color::Reset))?,
+            }
+            self.pen = pen.clone();
+        }
+
+        Ok(())
+    }
+
+    fn move_to(&mut self, x: usize, row: usize, out: &mut dyn Write) -> io::Result<()> {
+        let (mut column, current) = self.cursor;
+
+        if row < current {
+            write!(out, "{}", cursor::Up((current - row) as u16))?;
+        } else if row > current {
+            // Down stops at the bottom of the terminal, a newline scrolls instead
+            let existing = row.min(self.rows - 1).max(current);
+            if existing > current {
+                write!(out, "{}", cursor::Down((existing - current) as u16))?;
+            }
+            for _ in existing..row {
+                let newline = if column == 0 { "\n" } else { "\r\n" };
+                write!(out, "{}", newline)?;
+                column = 0;
+            }
+            self.rows = self.rows.max(row + 1);
+        }
+
+        if x == 0 && column != 0 {
+            write!(out, "\r")?;
+        } else if x > column {
+            write!(out, "{}", cursor::Right((x - column) as u16))?;
+        } else if x < column {
+            write!(out, "{}", cursor::Left((column - x) as u16))?;
+        }
+
+        self.cursor = (x, row);
+
+        Ok(())
+    }
+
+    /// Writes `cells` where the cursor is.
+    fn write_cells(
+        &mut self,
+        cells: &[Cell],
+        columns: usize,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        for cell in cells.iter().filter(|cell| !cell.is_continuation()) {
+            self.set_pen(&cell.pen, out)?;
+            write!(out, "{}", cell.text)?;
+            self.cursor.0 += text::grapheme_width(&cell.text);
+        }
+
+        // The cursor waits on the last column of a full row, return to a known place
+        if self.cursor.0 >= columns {
+            write!(out, "\r")?;
+            self.cursor.0 = 0;
+        }
+
+        Ok(())
+    }
+
+    pub fn present(&mut self, screen: &Screen, out: &mut dyn Write) -> io::Result<()> {
+        let (columns, _) = screen.size();
+        let columns = usize::from(columns);
+        let rows = screen.rows();
+
+        for row in 0..rows.len().max(self.drawn.len()) {
+            let new: &[Cell] = rows.get(row).map(Vec::as_slice).unwrap_or(&[]);
+            let old: Vec<Cell> = self.drawn.get(row).cloned().unwrap_or_default();
+
+            let differs = |x: &usize| new.get(*x) != old.get(*x);
+            let first = match (0..new.len().max(old.len())).find(differs) {
+                Some(first) => first,
+                None => continue,
+            };
+
+            if let Some(last) = (first..new.len()).rev().find(differs) {
+                // Start on the wide character a continuation belongs to
+                let mut start = first;
+                while start > 0 && new[start].is_continuation() {
+                    start -= 1;
+                }
+
+                self.move_to(start, row, out)?;
+                self.write_cells(&new[start..=last], columns, out)?;
+            }
+
+            if old.len() > new.len() {
+                self.move_to(new.len(), row, out)?;
+                self.set_pen(&None, out)?;
+                write!(out, "{}", clear::UntilNewline)?;
+            }
+        }
+
+        self.drawn = rows.to_vec();
+
+        let (x, row) = screen.cursor_cell();
+        self.move_to(x, row, out)?;
+        self.set_pen(&None, out)?;
+
+        out.flush()
+    }
+
+    /// Leaves the region below its last row, so output continues on a fresh row and the next
+    /// screen starts a new region.
+    pub fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        debug!("Finish a region of {} rows", self.drawn.len());
+
+        let last = self.rows - 1;
+        self.move_to(0, last, out)?;
+        self.set_pen(&None, out)?;
+        write!(out, "\r\n")?;
+
+        *self = Self::new();
+
+        out.flush()
+    }
+
+    /// Clears the region, e.g. after a resize rewrapped it, so the next screen is drawn anew.
+    pub fn invalidate(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let (_, row) = self.cursor;
+        if row > 0 {
+            write!(out, "{}", cursor::Up(row as u16))?;
+        }
+        self.set_pen(&None, out)?;
+        write!(out, "\r{}", clear::AfterCursor)?;
+
+        *self = Self::new();
+
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTerminal;
+
+    fn screen(lines: &[&str]) -> Screen {
+        let mut screen = Screen::new(10, 5);
+        for (index, line) in lines.iter().enumerate() {
+            if index > 0 {
+                screen.newline();
+            }
+            screen.in_color(None).write_text(line);
+        }
+        screen
+    }
+
+    #[test]
+    fn only_changed_cells_are_written() {
+        let term = MockTerminal::shared(10, 5);
+        let mut device = MockTerminal::device(&term);
+        let mut renderer = Renderer::new();
+
+        renderer
+            .present(&screen(&["> hello"]), &mut device)
+            .unwrap();
+        assert_eq!(term.borrow().lines(), ["> hello"]);
+
+        let mut written = Vec::new();
+        renderer
+            .present(&screen(&["> help"]), &mut written)
+            .unwrap();
+        device.write_all(&written).unwrap();
+
+        assert_eq!(term.borrow().lines(), ["> help"]);
+        assert!(!String::from_utf8_lossy(&written).contains("hel"));
+    }
+
+    #[test]
+    fn rows_are_added_and_removed() {
+        let term = MockTerminal::shared(10, 5);
+        let mut device = MockTerminal::device(&term);
+        let mut renderer = Renderer::new();
+
+        renderer
+            .present(&screen(&["> a", "b", "c"]), &mut device)
+            .unwrap();
+        assert_eq!(term.borrow().lines(), ["> a", "b", "c"]);
+        assert_eq!(term.borrow().cursor_cell(), (1, 2));
+
+        renderer.present(&screen(&["> a"]), &mut device).unwrap();
+        assert_eq!(term.borrow().lines(), ["> a"]);
+        assert_eq!(term.borrow().cursor_cell(), (3, 0));
+
+        renderer.finish(&mut device).unwrap();
+        assert_eq!(term.borrow().cursor_cell(), (0, 3));
+    }
+}
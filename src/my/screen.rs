@@ -0,0 +1,325 @@
+use crate::drawable::Drawable;
+use crate::shell::{self, CursorPos};
+use crate::text;
+use log::debug;
+use std::fmt;
+use termion::color::{self, Color};
+use unicode_width::UnicodeWidthChar;
+
+/// A color kept as the escape sequences which select it, so cells can store and compare it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pen {
+    fg: String,
+    bg: String,
+}
+
+impl Pen {
+    pub fn new(color: &dyn Color) -> Self {
+        Self {
+            fg: color::Fg(color).to_string(),
+            bg: color::Bg(color).to_string(),
+        }
+    }
+}
+
+impl Color for Pen {
+    fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.fg)
+    }
+
+    fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.bg)
+    }
+}
+
+/// One column of the screen. The column behind a wide character is a continuation without text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub text: String,
+    pub pen: Option<Pen>,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Self {
+            text: String::from(" "),
+            pen: None,
+        }
+    }
+
+    fn continuation(pen: Option<Pen>) -> Self {
+        Self {
+            text: String::new(),
+            pen,
+        }
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+/// A virtual screen the prompt and the line are drawn on, before the `Renderer` brings the
+/// changes to the terminal. Rows grow as they are written and wrap at `columns`.
+pub struct Screen {
+    columns: usize,
+    height: u16,
+    rows: Vec<Vec<Cell>>,
+    cursor_pos: CursorPos,
+    saved_pos: CursorPos,
+    pen: Option<Pen>,
+}
+
+impl Screen {
+    pub fn new(columns: u16, height: u16) -> Self {
+        Self {
+            columns: usize::from(columns).max(1),
+            height,
+            rows: Vec::new(),
+            cursor_pos: CursorPos::new(),
+            saved_pos: CursorPos::new(),
+            pen: None,
+        }
+    }
+
+    pub fn rows(&self) -> &[Vec<Cell>] {
+        &self.rows
+    }
+
+    /// The cursor as column and row, both counted from 0.
+    pub fn cursor_cell(&self) -> (usize, usize) {
+        (
+            usize::from(self.cursor_pos.x),
+            usize::from(self.cursor_pos.y.max(1) - 1),
+        )
+    }
+
+    /// The text of every row without trailing blanks.
+    pub fn lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.text.as_str()).collect();
+                line.trim_end().to_owned()
+            })
+            .collect()
+    }
+
+    fn row_mut(&mut self, row: usize) -> &mut Vec<Cell> {
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Vec::new());
+        }
+
+        &mut self.rows[row]
+    }
+
+    fn put(&mut self, grapheme: &str) {
+        // A line break in the text, e.g. of a pasted line, continues on the next row
+        if grapheme == "\n" {
+            shell::Terminal::newline(self);
+            return;
+        }
+
+        let width = text::grapheme_width(grapheme);
+        let (x, row) = self.cursor_cell();
+
+        // Zero width characters belong to the cell in front of them
+        if width == 0 {
+            if let Some(cell) = x.checked_sub(1).and_then(|x| self.row_mut(row).get_mut(x)) {
+                cell.text += grapheme;
+            }
+            return;
+        }
+
+        let (mut x, mut row) = (x, row);
+        if x + width > self.columns {
+            x = 0;
+            row += 1;
+        }
+
+        let pen = self.pen.clone();
+        let cells = self.row_mut(row);
+        if cells.len() < x + width {
+            cells.resize(x + width, Cell::blank());
+        }
+        cells[x] = Cell {
+            text: grapheme.to_owned(),
+            pen: pen.clone(),
+        };
+        for cell in cells[x + 1..x + width].iter_mut() {
+            *cell = Cell::continuation(pen.clone());
+        }
+
+        x += width;
+        if x >= self.columns {
+            x = 0;
+            row += 1;
+        }
+        self.cursor_pos.x = x as u16;
+        self.cursor_pos.y = row as u16 + 1;
+    }
+}
+
+impl shell::Write for Screen {
+    fn write(&mut self, ch: char) {
+        if ch == '\n' || ch.width().is_some() {
+            self.put(ch.encode_utf8(&mut [0; 4]));
+        }
+    }
+
+    fn write_text(&mut self, text: &str) {
+        for grapheme in text::graphemes(text) {
+            self.put(grapheme);
+        }
+
+        self.pen = None;
+    }
+}
+
+impl shell::Terminal for Screen {
+    fn clear(&mut self) -> &mut dyn shell::Clear {
+        self
+    }
+
+    fn cursor(&mut self) -> &mut dyn shell::Cursor {
+        self
+    }
+
+    fn in_color(&mut self, color: Option<&dyn Color>) -> &mut dyn shell::Write {
+        self.pen = color.map(Pen::new);
+
+        self
+    }
+
+    fn newline(&mut self) -> &mut dyn shell::Terminal {
+        self.cursor_pos.x = 0;
+        self.cursor_pos.y += 1;
+
+        let (_, row) = self.cursor_cell();
+        self.row_mut(row);
+
+        self
+    }
+
+    fn flush(&mut self) -> &mut dyn shell::Terminal {
+        self
+    }
+
+    fn size(&self) -> (u16, u16) {
+        (self.columns as u16, self.height)
+    }
+
+    /// A screen only holds what is drawn.
+    fn copy_to_clipboard(&mut self, _: &str) {}
+
+    fn suspend(&mut self) {}
+
+    fn resume(&mut self) {}
+}
+
+impl shell::Clear for Screen {
+    fn all(&mut self) {
+        self.rows.clear();
+        self.cursor_pos = CursorPos::new();
+    }
+
+    fn line(&mut self) {
+        let (_, row) = self.cursor_cell();
+        self.row_mut(row).clear();
+        self.cursor_pos.x = 0;
+    }
+}
+
+impl shell::Cursor for Screen {
+    fn set_to(&mut self, x: u16, y: u16) -> &mut dyn shell::Cursor {
+        self.cursor_pos.x = x;
+        self.cursor_pos.y = y.max(1);
+
+        self
+    }
+
+    fn get(&self) -> (u16, u16) {
+        self.cursor_pos.get()
+    }
+
+    fn clear_after(&mut self) -> &mut dyn shell::Cursor {
+        let (x, row) = self.cursor_cell();
+        self.row_mut(row).truncate(x);
+        self.rows.truncate(row + 1);
+
+        self
+    }
+
+    fn set_x(&mut self, x: u16) -> &mut dyn shell::Cursor {
+        self.cursor_pos.x = x;
+
+        self
+    }
+
+    fn set_y(&mut self, y: u16) -> &mut dyn shell::Cursor {
+        self.cursor_pos.y = y.max(1);
+
+        self
+    }
+
+    fn move_left(&mut self, x: u16) -> &mut dyn shell::Cursor {
+        self.cursor_pos.x = self.cursor_pos.x.saturating_sub(x);
+
+        self
+    }
+
+    fn move_right(&mut self, x: u16) -> &mut dyn shell::Cursor {
+        self.cursor_pos.x = (self.cursor_pos.x + x).min(self.columns as u16 - 1);
+
+        self
+    }
+
+    fn move_up(&mut self, y: u16) -> &mut dyn shell::Cursor {
+        self.cursor_pos.y = self.cursor_pos.y.saturating_sub(y).max(1);
+
+        self
+    }
+
+    fn move_down(&mut self, y: u16) -> &mut dyn shell::Cursor {
+        self.cursor_pos.y += y;
+
+        self
+    }
+
+    fn save(&mut self) -> &mut dyn shell::Cursor {
+        self.saved_pos = self.cursor_pos;
+
+        self
+    }
+
+    fn restore(&mut self) -> &mut dyn shell::Cursor {
+        self.cursor_pos = self.saved_pos;
+
+        self
+    }
+}
+
+/// Writes the rows as plain output, e.g. for a builtin which shows a prompt.
+impl Drawable for Screen {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        debug!("Draw a screen of {} rows", self.rows.len());
+
+        for (index, row) in self.rows.iter().enumerate() {
+            if index > 0 {
+                term.newline();
+            }
+
+            // One color change for every run of cells with the same pen
+            let mut cells = row.iter().filter(|cell| !cell.is_continuation()).peekable();
+            while let Some(cell) = cells.next() {
+                let mut text = cell.text.clone();
+                while let Some(next) = cells.next_if(|next| next.pen == cell.pen) {
+                    text += &next.text;
+                }
+
+                let color = cell.pen.as_ref().map(|pen| pen as &dyn Color);
+                term.in_color(color).write_text(&text);
+            }
+        }
+    }
+}
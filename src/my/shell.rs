@@ -1,45 +1,508 @@
 use crate::behaviour::{ExecutableWordColorizer, WordColorizeBehaviour};
-use crate::config::Config;
+use crate::builtin::{self, Builtins};
+use crate::config::{self, Config, Editing};
 use crate::drawable::Drawable;
+use crate::editor;
 use crate::my;
+use crate::my::input::{Event, Input};
+use crate::my::keymap::{Action, Binding, Dispatch};
+use crate::path::OsPath;
 use crate::shell::line::Line;
 use crate::shell::{self, Terminal};
+use crate::signal;
+use crate::theme::Themes;
+use log::debug;
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use termion::color::Rgb;
+use termion::event::{Key, MouseButton, MouseEvent};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const CURSOR_POS_TIMEOUT: Duration = Duration::from_millis(500);
+/// Some terminals ask the user before they hand out the clipboard
+const CLIPBOARD_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub struct Shell {
     terminal: my::Terminal,
     line: my::Line,
     prompt: shell::Prompt,
+    builtins: Builtins,
+    history: Rc<RefCell<my::History>>,
+    input: Input,
+    context: shell::Context,
+    /// Shared with `set -o`, which switches it at runtime
+    editing: Rc<Cell<Editing>>,
+    vi: my::Vi,
+    /// Shared with `bind`, which changes it at runtime
+    keymap: Rc<RefCell<my::Keymap>>,
+    /// The commands builtins like `fc` leave to run after them
+    queue: Rc<RefCell<Vec<String>>>,
+    /// The editor of the config, the one of the user if there is none
+    editor: Option<String>,
+    /// The row of the region the line starts on
+    line_row: usize,
+    /// The terminal row of the region and where the selection started, while the mouse drags
+    drag: Option<(u16, usize)>,
+    /// Set by an unbound Esc of the emacs keys, which leaves the shell
+    quit: bool,
 }
 
 impl Shell {
     pub fn new(config: &Config) -> Self {
+        signal::catch_interrupts();
+        let mut input = Input::new();
+        if let Some(fd) = signal::watch_resize() {
+            input.watch_resize(fd);
+        }
+
+        Self::with(
+            config,
+            my::Terminal::new(),
+            input,
+            Themes::load(),
+            OsPath::load(),
+        )
+    }
+
+    /// Runs on the given terminal and input with the given themes and executables, without taking
+    /// over the signals of the process.
+    pub fn with(
+        config: &Config,
+        terminal: my::Terminal,
+        input: Input,
+        themes: Themes,
+        os_path: OsPath,
+    ) -> Self {
+        let themed = themes.apply(&config.theme.name, config);
+
+        let os_path = Rc::new(os_path);
+        let editing = Rc::new(Cell::new(config.line.editing));
+        let keymap = Rc::new(RefCell::new(my::Keymap::new(&config.keys)));
+        let history = Rc::new(RefCell::new(my::History::new()));
+        let queue = Rc::new(RefCell::new(Vec::new()));
+
+        let mut builtins = Builtins::new();
+        builtins.add(Box::new(builtin::Theme::new(config, themes)));
+        builtins.add(Box::new(builtin::Set::new(Rc::clone(&editing))));
+        builtins.add(Box::new(builtin::Bind::new(Rc::clone(&keymap))));
+        builtins.add(Box::new(builtin::Clip::new()));
+        builtins.add(Box::new(builtin::Which::new(Rc::clone(&os_path))));
+        builtins.add(Box::new(builtin::Diagnostics::new(Rc::clone(&os_path))));
+        builtins.add(Box::new(builtin::Fc::new(
+            Rc::clone(&history),
+            Rc::clone(&queue),
+            config.line.editor.clone(),
+        )));
+
+        let config = &themed;
         let mut behaviour = WordColorizeBehaviour::new(&config.colorize);
-        behaviour.add_colorizer(Box::new(ExecutableWordColorizer::new()));
+        behaviour.add_colorizer(Box::new(ExecutableWordColorizer::new(os_path)));
 
         let mut line = my::Line::new(&config.line);
         line.add_behaviour(Box::new(behaviour));
+        line.set_selection_color(
+            config
+                .colorize
+                .selection
+                .map(|config::Rgb(r, g, b)| Rgb(r, g, b)),
+        );
 
         Self {
-            terminal: my::Terminal::new(),
+            terminal,
             line,
             prompt: shell::Prompt::new(&config.prompt),
+            builtins,
+            history,
+            input,
+            context: shell::Context::default(),
+            editing,
+            vi: my::Vi::new(),
+            keymap,
+            queue,
+            editor: config.line.editor.clone(),
+            line_row: 0,
+            drag: None,
+            quit: false,
         }
     }
 
-    fn newline(&mut self) {
-        self.terminal.newline();
-        self.line.reset();
+    /// Runs a builtin or a program. The words are split on whitespace, quotes and escapes are
+    /// not understood yet, so `echo "a b"` passes `"a` and `b"`.
+    fn execute(&mut self, input: &str) {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let (name, args) = match words.split_first() {
+            Some((name, args)) => (name, args),
+            None => return,
+        };
+
+        let start = Instant::now();
+        let exit = if let Some(builtin) = self.builtins.find(name) {
+            shell::Exit::Code(builtin.run(args, &mut self.terminal))
+        } else {
+            self.spawn(name, args)
+        };
+
+        self.context.last_command = Some(shell::LastCommand {
+            exit,
+            duration: start.elapsed(),
+        });
+    }
+
+    fn spawn(&mut self, name: &str, args: &[&str]) -> shell::Exit {
+        use std::io::ErrorKind;
+        use std::process::Command;
+
+        self.terminal.suspend();
+        let status = Command::new(name).args(args).status();
+        self.terminal.resume();
+        self.sync_cursor();
+
+        match status {
+            Ok(status) => shell::Exit::from(status),
+            Err(e) => {
+                let (message, code) = if e.kind() == ErrorKind::NotFound {
+                    (format!("mysh: command not found: {}", name), 127)
+                } else {
+                    (format!("mysh: {}: {}", name, e), 126)
+                };
+
+                self.terminal.in_color(None).write_text(&message);
+                self.terminal.newline();
+
+                shell::Exit::Code(code)
+            }
+        }
+    }
+
+    /// Asks the terminal where the output of a child left the cursor and starts on a fresh line.
+    fn sync_cursor(&mut self) {
+        self.terminal.request_cursor_pos();
+        if let Some((x, y)) = self.input.wait_for_cursor_pos(CURSOR_POS_TIMEOUT) {
+            self.terminal.cursor().set_to(x.saturating_sub(1), y);
+            if x > 1 {
+                self.terminal.newline();
+            }
+        }
+    }
+
+    fn on_key(&mut self, key: Key) {
+        // The rest of a sequence like `ctrl-x w` belongs to the keymap, not to vi
+        let key = if self.editing.get() == Editing::Vi && !self.keymap.borrow().is_pending() {
+            match self.vi.on_key(key, &mut self.line) {
+                Some(key) => key,
+                None => return,
+            }
+        } else {
+            key
+        };
+
+        let dispatch = self.keymap.borrow_mut().dispatch(key);
+        let pending = matches!(dispatch, Dispatch::Pending);
+        match dispatch {
+            Dispatch::Pending => {}
+            Dispatch::Bound(Binding::Action(action)) => self.perform(action),
+            Dispatch::Bound(Binding::Command(command)) => self.run_bound(&command),
+            Dispatch::Unbound(keys) => match keys[..] {
+                [Key::Char(ch)] if ch != '\n' => self.line.insert(ch),
+                [Key::Esc] if self.editing.get() == Editing::Emacs => self.quit = true,
+                _ => debug!("Unbound keys {:?}", keys),
+            },
+        }
+
+        // A selection lasts until the next key, which may kill or copy it
+        if !pending {
+            self.line.set_mark(None);
+        }
+
+        if self.editing.get() == Editing::Vi {
+            self.vi.fit_cursor(&mut self.line);
+        }
+    }
+
+    /// The selection of vi's visual mode or the region of the emacs keys.
+    fn selection(&self) -> Option<Range<usize>> {
+        match self.editing.get() {
+            Editing::Vi => self.vi.selection(&self.line),
+            Editing::Emacs => self.line.selection(),
+        }
+    }
+
+    /// A click moves the cursor, a drag selects from there. The selection is vi's visual mode
+    /// or the region of the emacs keys.
+    fn on_mouse(&mut self, event: MouseEvent) {
+        match event {
+            MouseEvent::Press(MouseButton::Left, x, y) => {
+                self.drag = None;
+                let origin = match self.region_origin() {
+                    Some(origin) => origin,
+                    None => return,
+                };
+
+                if let Some(index) = self.line_index_at(origin, x, y) {
+                    self.line.set_index(index);
+                    self.line.set_mark(None);
+                    if self.editing.get() == Editing::Vi {
+                        self.vi.select(None);
+                        self.vi.fit_cursor(&mut self.line);
+                    }
+                    self.drag = Some((origin, index));
+                }
+            }
+            MouseEvent::Hold(x, y) => {
+                let (origin, anchor) = match self.drag {
+                    Some(drag) => drag,
+                    None => return,
+                };
+
+                if let Some(index) = self.line_index_at(origin, x, y) {
+                    self.line.set_index(index);
+                    match self.editing.get() {
+                        Editing::Vi => self.vi.select(Some(anchor)),
+                        Editing::Emacs => self.line.set_mark(Some(anchor)),
+                    }
+                }
+            }
+            MouseEvent::Release(..) => self.drag = None,
+            MouseEvent::Press(..) => {}
+        }
+    }
+
+    /// Asks the terminal for the row the region starts on, since it scrolls with the output.
+    fn region_origin(&mut self) -> Option<u16> {
+        self.terminal.request_cursor_pos();
+        let (_, y) = self.input.wait_for_cursor_pos(CURSOR_POS_TIMEOUT)?;
+        let (_, row) = self.terminal.region_cursor();
+
+        y.checked_sub(row as u16)
+    }
+
+    /// The grapheme cluster of the line at column `x` and row `y` of the terminal.
+    fn line_index_at(&self, origin: u16, x: u16, y: u16) -> Option<usize> {
+        let row = usize::from(y.checked_sub(origin)?).checked_sub(self.line_row)?;
+
+        self.line.index_at(usize::from(x.saturating_sub(1)), row)
+    }
+
+    /// Inserts a paste as one change, its line breaks stay in the line instead of accepting it.
+    fn paste(&mut self, text: &str) {
+        debug!("Paste {:?}", text);
+        self.line.insert_text(text);
+
+        if self.editing.get() == Editing::Vi {
+            self.vi.fit_cursor(&mut self.line);
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::AcceptLine => {
+                let input = self.line.text();
+                self.submit();
+                // Every row of a pasted text is a command of its own
+                for command in input.lines() {
+                    self.execute(command);
+                }
+                // Added behind its commands, so `fc` finds the entries before it
+                self.history.borrow_mut().add(&input);
+                self.run_queued();
+                self.render_prompt();
+            }
+            Action::BackwardChar => self.line.cursor().move_left(),
+            Action::ForwardChar => self.line.cursor().move_right(),
+            Action::BackwardWord => self.line.move_word_left(),
+            Action::ForwardWord => self.line.move_word_right(),
+            Action::BeginningOfLine => self.line.move_to_start(),
+            Action::EndOfLine => self.line.move_to_end(),
+            Action::BackwardDeleteChar => self.line.remove_after(),
+            Action::DeleteChar => self.line.remove_before(),
+            Action::UnixWordRubout => self.line.kill_word_before(),
+            Action::KillWord => self.line.kill_word_after(),
+            Action::KillLine => self.line.kill_to_end(),
+            Action::UnixLineDiscard => self.line.kill_to_start(),
+            Action::Yank => self.line.yank(),
+            Action::YankPop => self.line.yank_pop(),
+            Action::KillRegion => {
+                if let Some(range) = self.selection() {
+                    self.line.kill(range);
+                }
+            }
+            Action::CopyRegionAsKill => {
+                if let Some(range) = self.selection() {
+                    self.line.copy(range);
+                }
+            }
+            Action::TransposeChars => self.line.transpose(),
+            Action::PreviousHistory => {
+                if !self.line.move_up() {
+                    if let Some(entry) = self.history.borrow_mut().previous(&self.line.text()) {
+                        self.line.set_text(entry);
+                    }
+                }
+            }
+            Action::NextHistory => {
+                if !self.line.move_down() {
+                    if let Some(entry) = self.history.borrow_mut().next() {
+                        self.line.set_text(entry);
+                    }
+                }
+            }
+            Action::Undo => self.line.undo(),
+            Action::Redo => self.line.redo(),
+            Action::ClipboardCopy => {
+                let text = match self.selection() {
+                    Some(range) => self.line.graphemes()[range].concat(),
+                    None => self.line.text(),
+                };
+                self.terminal.copy_to_clipboard(&text);
+                self.vi.select(None);
+            }
+            Action::ClipboardCopyKill => {
+                if let Some(killed) = self.line.killed().map(str::to_owned) {
+                    self.terminal.copy_to_clipboard(&killed);
+                }
+            }
+            Action::ClipboardPaste => {
+                self.terminal.request_clipboard();
+                match self.input.wait_for_clipboard(CLIPBOARD_TIMEOUT) {
+                    Some(text) => self.paste(&text),
+                    None => debug!("The terminal did not answer with the clipboard"),
+                }
+            }
+            Action::EditCommandLine => self.edit_line(),
+        }
+    }
+
+    /// Runs the `command` of a binding below the line, which is kept for editing on.
+    fn run_bound(&mut self, command: &str) {
+        let screen = self.draw(false);
+        self.terminal.present(&screen);
+        self.terminal.finish();
+
+        self.execute(command);
+        self.run_queued();
+        self.render_prompt();
+    }
+
+    /// Runs the commands a builtin left, shown and added to the history as if they were typed.
+    fn run_queued(&mut self) {
+        let queued = std::mem::take(&mut *self.queue.borrow_mut());
+        if queued.is_empty() {
+            return;
+        }
+
+        // The editor of `fc` may have left the cursor anywhere
+        self.sync_cursor();
+        for input in queued {
+            for command in input.lines() {
+                self.terminal.in_color(None).write_text(command);
+                self.terminal.newline();
+                self.execute(command);
+            }
+            self.history.borrow_mut().add(&input);
+        }
+
+        // A queued `fc` does not run another round
+        self.queue.borrow_mut().clear();
+    }
+
+    /// Opens the line in the editor of the user and takes back what was saved into it.
+    fn edit_line(&mut self) {
+        let screen = self.draw(false);
+        self.terminal.present(&screen);
+        self.terminal.finish();
+
+        self.terminal.suspend();
+        let edited = editor::edit(&self.line.text(), self.editor.as_deref());
+        self.terminal.resume();
+        self.sync_cursor();
+
+        match edited {
+            Ok(text) => {
+                let len = self.line.graphemes().len();
+                self.line.replace(0..len, &text);
+                self.line.move_to_end();
+            }
+            Err(e) => {
+                self.terminal
+                    .in_color(None)
+                    .write_text(&format!("mysh: {}", e));
+                self.terminal.newline();
+            }
+        }
+    }
+
+    /// Shows the editing mode in the carets and by the shape of the cursor. Vi starts over in
+    /// the insert mode while emacs keys are used, so switching to it starts there.
+    fn sync_mode(&mut self) {
+        let mode = match self.editing.get() {
+            Editing::Emacs => {
+                self.vi.reset();
+                shell::Mode::Emacs
+            }
+            Editing::Vi => self.vi.mode(),
+        };
+
+        if mode != self.context.mode {
+            self.context.mode = mode;
+            self.prompt.refresh_caret(&self.context);
+        }
     }
 
     fn render_prompt(&mut self) {
-        self.prompt.render_on(&mut self.terminal);
-        self.line.set_padding(self.terminal.cursor());
-        self.terminal.flush();
+        self.prompt.refresh(&self.context);
+        self.render();
     }
 
-    fn render_line(&mut self) {
-        self.line.render_on(&mut self.terminal);
-        self.terminal.flush();
+    /// Draws the prompt, or just its transient caret, and the line on a fresh screen.
+    fn draw(&mut self, transient: bool) -> my::Screen {
+        let (columns, height) = self.terminal.size();
+        let mut screen = my::Screen::new(columns, height);
+
+        match self.prompt.transient() {
+            Some(caret) if transient => caret.render_on(&mut screen),
+            _ => self.prompt.render_on(&mut screen),
+        }
+        self.line.set_padding(screen.cursor());
+        self.line_row = screen.cursor_cell().1;
+        self.line.render_on(&mut screen);
+        if !transient {
+            self.prompt.render_right_on(&mut screen, self.line.end());
+        }
+
+        screen
+    }
+
+    /// Brings the prompt and the line up to date, only what changed reaches the terminal.
+    fn render(&mut self) {
+        self.sync_mode();
+
+        let screen = self.draw(false);
+        self.terminal.present(&screen);
+        self.terminal.set_cursor_shape(match self.context.mode {
+            shell::Mode::Emacs => my::CursorShape::Default,
+            shell::Mode::Insert => my::CursorShape::Bar,
+            shell::Mode::Normal | shell::Mode::Visual => my::CursorShape::Block,
+        });
+    }
+
+    /// Shows the submitted line for the last time and moves below it.
+    fn submit(&mut self) {
+        self.line.move_to_end();
+
+        let screen = self.draw(true);
+        self.terminal.present(&screen);
+        self.terminal.finish();
+        self.line.reset();
+        self.vi.reset();
+    }
+
+    /// Draws the prompt and the line anew for the new width.
+    fn on_resize(&mut self) {
+        self.terminal.update_size();
+        self.terminal.invalidate();
+        self.render();
     }
 }
 
@@ -50,36 +513,314 @@ impl shell::Shell for Shell {
     }
 
     fn run(&mut self) {
-        use std::io::stdin;
-        use termion::event::Key;
-        use termion::input::TermRead;
-
-        'L1: loop {
-            self.render_prompt();
-
-            for key in stdin().keys() {
-                match key.unwrap() {
-                    Key::Esc => break 'L1,
-                    Key::Char(ch) => {
-                        if ch == '\n' {
-                            self.newline();
-                            self.render_prompt();
-                        } else {
-                            self.line.insert(ch);
-                        }
-                    }
-                    Key::Backspace => self.line.remove_after(),
-                    Key::Delete => self.line.remove_before(),
-                    Key::Alt(c) => println!("Alt-{}", c),
-                    Key::Ctrl(c) => println!("Ctrl-{}", c),
-                    Key::Left => self.line.cursor().move_left(),
-                    Key::Right => self.line.cursor().move_right(),
-                    Key::Down => println!("<down>"),
-                    _ => println!("Other"),
+        self.render_prompt();
+
+        while !self.quit && !self.input.is_eof() {
+            match self.input.next(POLL_INTERVAL) {
+                Some(Event::Key(key)) => {
+                    self.on_key(key);
+                    self.render();
+                }
+                Some(Event::Mouse(event)) => {
+                    self.on_mouse(event);
+                    self.render();
                 }
+                Some(Event::Paste(text)) => {
+                    self.paste(&text);
+                    self.render();
+                }
+                Some(Event::Resize) => self.on_resize(),
+                Some(Event::CursorPos(..)) | Some(Event::Clipboard(..)) | None => {}
+            }
 
-                self.render_line();
+            if self.prompt.poll() {
+                self.render();
             }
         }
+
+        self.terminal.set_cursor_shape(my::CursorShape::Default);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTerminal;
+    use crate::shell::Shell as _;
+
+    const CONFIG: &str = r#"
+        [prompt]
+        widgets = ["caret"]
+        caret = { user = "> ", admin = "> " }
+
+        [line]
+        capacity = 100
+        left_padding = 0
+
+        [colorize]
+    "#;
+
+    /// Types `keys` into a shell on a terminal of `columns` x `height` and returns the screen.
+    fn run(keys: &[u8], columns: u16, height: u16) -> MockTerminal {
+        run_with(CONFIG, keys, columns, height)
+    }
+
+    fn run_with(config: &str, keys: &[u8], columns: u16, height: u16) -> MockTerminal {
+        let config: Config = toml::from_str(config).unwrap();
+        let term = MockTerminal::shared(columns, height);
+
+        let terminal = my::Terminal::with_device(MockTerminal::device(&term));
+        // Only the bundled themes and no executables, whatever the environment has
+        let mut shell = Shell::with(
+            &config,
+            terminal,
+            Input::from_bytes(keys),
+            Themes::with_dir(None),
+            OsPath::with_path(None),
+        );
+        shell.run();
+        drop(shell);
+
+        match std::rc::Rc::try_unwrap(term) {
+            Ok(term) => term.into_inner(),
+            Err(_) => panic!("the shell still holds the terminal"),
+        }
+    }
+
+    #[test]
+    fn keys_edit_the_line() {
+        let term = run(b"abc\x1b[D\x1b[D\x7fX", 40, 10);
+
+        assert_eq!(term.lines(), ["> Xbc"]);
+        assert_eq!(term.cursor_cell(), (3, 0));
+    }
+
+    #[test]
+    fn readline_keys_edit_the_line() {
+        let term = run(b"world\x01hello \x05!\x1bb\x0b\x1b[H\x19", 40, 10);
+
+        assert_eq!(term.lines(), ["> world!hello"]);
+        assert_eq!(term.cursor_cell(), (8, 0));
+    }
+
+    #[test]
+    fn set_switches_to_vi_keys() {
+        let term = run(b"set -o vi\rfoo bar\x1bbdw", 40, 10);
+
+        assert_eq!(term.lines(), ["> set -o vi", "> foo"]);
+        assert_eq!(term.cursor_cell(), (5, 1));
+    }
+
+    #[test]
+    fn the_caret_shows_the_vi_mode() {
+        let config = r#"
+            [prompt]
+            widgets = ["caret"]
+            caret = { user = "> ", admin = "> ", normal = ": " }
+
+            [line]
+            capacity = 100
+            left_padding = 0
+            editing = "vi"
+
+            [colorize]
+        "#;
+
+        assert_eq!(run_with(config, b"ls", 40, 10).lines(), ["> ls"]);
+        assert_eq!(run_with(config, b"ls\x1b", 40, 10).lines(), [": ls"]);
+        assert_eq!(run_with(config, b"ls\x1bA", 40, 10).lines(), ["> ls"]);
+    }
+
+    #[test]
+    fn undo_brings_back_a_discarded_line() {
+        let term = run(b"echo hi\x15\x1f", 40, 10);
+
+        assert_eq!(term.lines(), ["> echo hi"]);
+    }
+
+    #[test]
+    fn a_paste_keeps_its_line_breaks() {
+        let term = run(b"x\x1b[200~set -o\rset\x1b[201~", 40, 10);
+        assert_eq!(term.lines(), ["> xset -o", "set"]);
+        assert_eq!(term.cursor_cell(), (3, 1));
+
+        let term = run(b"x\x1b[200~set -o\rset\x1b[201~\x1f", 40, 10);
+        assert_eq!(term.lines(), ["> x"]);
+    }
+
+    #[test]
+    fn every_row_of_a_paste_runs_on_its_own() {
+        let term = run(b"\x1b[200~set -o vi\r\rset -o\x1b[201~\r", 40, 10);
+
+        assert_eq!(
+            term.lines(),
+            [
+                "> set -o vi",
+                "",
+                "set -o",
+                "emacs   off",
+                "vi      on",
+                ">"
+            ]
+        );
+    }
+
+    #[test]
+    fn a_click_moves_the_cursor() {
+        // Every click asks where the cursor is, the answer follows it
+        let term = run(b"echo hello\x1b[<0;8;1M\x1b[1;13R\x1b[<0;8;1mX", 40, 10);
+
+        assert_eq!(term.lines(), ["> echo Xhello"]);
+        assert_eq!(term.cursor_cell(), (8, 0));
+    }
+
+    #[test]
+    fn a_drag_selects_a_region() {
+        let drag = b"echo hello world\x1b[<0;8;1M\x1b[1;19R\x1b[<32;13;1M\x1b[<0;13;1m";
+
+        let term = run(drag, 40, 10);
+        let selection = Some(my::screen::Pen::new(&Rgb(97, 175, 239)));
+        assert_eq!(term.pen_at(6, 0), None);
+        assert_eq!(term.pen_at(7, 0), selection);
+        assert_eq!(term.pen_at(11, 0), selection);
+        assert_eq!(term.pen_at(12, 0), None);
+
+        let term = run(&[&drag[..], b"\x18\x17\x05 \x19"].concat(), 40, 10);
+        assert_eq!(term.lines(), ["> echo  world hello"]);
+
+        let config = CONFIG.replace("capacity = 100", "capacity = 100\nediting = \"vi\"");
+        let term = run_with(&config, &[&drag[..], b"d"].concat(), 40, 10);
+        assert_eq!(term.lines(), ["> echo world"]);
+    }
+
+    #[test]
+    fn the_clipboard_takes_the_line_or_its_selection() {
+        assert_eq!(
+            run(b"echo hi\x18w", 40, 10).clipboard.as_deref(),
+            Some("echo hi")
+        );
+
+        let select = b"echo hi\x1b[<0;3;1M\x1b[1;10R\x1b[<32;7;1M\x1b[<0;7;1m";
+        let term = run(&[&select[..], b"\x18w"].concat(), 40, 10);
+        assert_eq!(term.clipboard.as_deref(), Some("echo"));
+
+        let term = run(b"echo hi\x17\x18k", 40, 10);
+        assert_eq!(term.clipboard.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn a_paste_from_the_clipboard_is_inserted() {
+        let term = run(b"echo \x18y\x1b]52;c;aGk=\x07", 40, 10);
+
+        assert_eq!(term.lines(), ["> echo hi"]);
+    }
+
+    #[test]
+    fn clip_copies_the_output_of_a_command() {
+        let term = run(b"clip echo hi there\r", 40, 10);
+
+        assert_eq!(term.lines(), ["> clip echo hi there", "hi there", ">"]);
+        assert_eq!(term.clipboard.as_deref(), Some("hi there\n"));
+    }
+
+    #[test]
+    fn the_editor_changes_the_line_and_history_entries() {
+        let config = CONFIG.replace(
+            "capacity = 100",
+            "capacity = 100\neditor = \"sed -i s/vi/emacs/\"",
+        );
+
+        let term = run_with(&config, b"set -o vi\x18\x05", 40, 10);
+        assert_eq!(term.lines(), ["> set -o vi", "> set -o emacs"]);
+
+        let term = run_with(&config, b"set -o vi\rfc -l\rfc 1\rset -o\r", 40, 20);
+        assert_eq!(
+            term.lines(),
+            [
+                "> set -o vi",
+                "> fc -l",
+                "    1  set -o vi",
+                "> fc 1",
+                "set -o emacs",
+                "> set -o",
+                "emacs   on",
+                "vi      off",
+                ">"
+            ]
+        );
+    }
+
+    #[test]
+    fn bound_commands_run_below_the_line() {
+        let term = run(b"bind alt-x -- set -o\rab\x1bx", 40, 10);
+
+        assert_eq!(
+            term.lines(),
+            [
+                "> bind alt-x -- set -o",
+                "> ab",
+                "emacs   on",
+                "vi      off",
+                "> ab",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_bound_esc_does_not_leave_the_shell() {
+        let term = run(b"bind esc -- set -o\rab\x1b", 40, 10);
+
+        assert_eq!(
+            term.lines(),
+            [
+                "> bind esc -- set -o",
+                "> ab",
+                "emacs   on",
+                "vi      off",
+                "> ab",
+            ]
+        );
+    }
+
+    #[test]
+    fn long_lines_wrap() {
+        let term = run(b"echo 1234567890", 10, 5);
+
+        assert_eq!(term.lines(), ["> echo 123", "4567890"]);
+        assert_eq!(term.cursor_cell(), (7, 1));
+    }
+
+    #[test]
+    fn builtins_print_below_the_line() {
+        let term = run(b"theme list\r", 40, 10);
+        let lines = term.lines();
+
+        assert_eq!(lines[..2], ["> theme list", "* default"]);
+        assert_eq!(lines.last().map(String::as_str), Some(">"));
+        assert_eq!(term.cursor_cell(), (2, lines.len() - 1));
+    }
+
+    #[test]
+    fn which_looks_in_the_index_of_the_shell() {
+        let term = run(b"which ls\r", 40, 10);
+
+        // The test shell indexed no executables at all
+        assert_eq!(term.lines(), ["> which ls", "ls not found", ">"]);
+    }
+
+    #[test]
+    fn up_recalls_the_history() {
+        let term = run(b"mysh-test-a\rmysh-test-b\r\x1b[A\x1b[A", 60, 10);
+
+        assert_eq!(
+            term.lines(),
+            [
+                "> mysh-test-a",
+                "mysh: command not found: mysh-test-a",
+                "> mysh-test-b",
+                "mysh: command not found: mysh-test-b",
+                "> mysh-test-a",
+            ]
+        );
     }
 }
@@ -1,4 +1,7 @@
+use crate::clipboard;
+use crate::my::{Renderer, Screen};
 use crate::shell::{self, CursorPos};
+use crate::text;
 use log::debug;
 use std::io::{self, Stdout, Write};
 use termion::input::MouseTerminal;
@@ -7,19 +10,149 @@ use termion::{clear, color, cursor};
 
 type Term = MouseTerminal<RawTerminal<Stdout>>;
 
+/// Where the `Terminal` writes to, usually stdout in raw mode.
+pub trait Device: Write {
+    /// Leaves raw mode for a child.
+    fn suspend(&mut self) -> io::Result<()>;
+    fn resume(&mut self) -> io::Result<()>;
+    /// The number of columns and rows
+    fn size(&self) -> io::Result<(u16, u16)>;
+}
+
+impl Device for Term {
+    fn suspend(&mut self) -> io::Result<()> {
+        self.suspend_raw_mode()
+    }
+
+    fn resume(&mut self) -> io::Result<()> {
+        self.activate_raw_mode()
+    }
+
+    fn size(&self) -> io::Result<(u16, u16)> {
+        termion::terminal_size()
+    }
+}
+
+/// The shapes of the cursor, which tell the modes of vi apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorShape {
+    /// The one the user configured for the terminal
+    Default,
+    Block,
+    Bar,
+}
+
+const ENTER_MOUSE_SEQUENCE: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h";
+const EXIT_MOUSE_SEQUENCE: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+const ENTER_PASTE_SEQUENCE: &str = "\x1b[?2004h";
+const EXIT_PASTE_SEQUENCE: &str = "\x1b[?2004l";
+const REQUEST_CURSOR_POS: &str = "\x1b[6n";
+const DEFAULT_CURSOR_SHAPE: &str = "\x1b[0 q";
+
 pub struct Terminal {
-    terminal: Term,
+    terminal: Box<dyn Device>,
     cursor_pos: CursorPos,
+    saved_pos: CursorPos,
+    size: (u16, u16),
+    renderer: Renderer,
+    shape: CursorShape,
 }
 
 impl Terminal {
     pub fn new() -> Self {
         use termion::raw::IntoRawMode;
 
+        Self::with_device(Box::new(Term::from(io::stdout().into_raw_mode().unwrap())))
+    }
+
+    /// Takes over `device` and turns on bracketed paste, so a paste arrives as `Event::Paste`.
+    pub fn with_device(mut device: Box<dyn Device>) -> Self {
+        let size = device.size().unwrap_or((u16::MAX, u16::MAX));
+        write!(device, "{}", ENTER_PASTE_SEQUENCE).unwrap();
+        device.flush().unwrap();
+
         Self {
-            terminal: Term::from(io::stdout().into_raw_mode().unwrap()),
+            terminal: device,
             cursor_pos: CursorPos::new(),
+            saved_pos: CursorPos::new(),
+            size,
+            renderer: Renderer::new(),
+            shape: CursorShape::Default,
+        }
+    }
+
+    /// Asks for the size again after the terminal was resized.
+    pub fn update_size(&mut self) {
+        self.size = self.terminal.size().unwrap_or(self.size);
+        debug!("Resized to {:?}", self.size);
+
+        let (_, height) = self.size;
+        self.cursor_pos.y = self.cursor_pos.y.min(height);
+    }
+
+    /// Shows `screen` in the region of the prompt and the line.
+    pub fn present(&mut self, screen: &Screen) {
+        self.renderer.present(screen, &mut self.terminal).unwrap();
+    }
+
+    /// Moves below the region, so the next `present` starts a new one.
+    pub fn finish(&mut self) {
+        self.renderer.finish(&mut self.terminal).unwrap();
+        self.cursor_pos.x = 0;
+    }
+
+    /// Clears the region, so the next `present` draws everything again.
+    pub fn invalidate(&mut self) {
+        self.renderer.invalidate(&mut self.terminal).unwrap();
+    }
+
+    /// The cursor in the region of the last `present`, with the rows counted from its first one.
+    pub fn region_cursor(&self) -> (usize, usize) {
+        self.renderer.cursor()
+    }
+
+    pub fn set_cursor_shape(&mut self, shape: CursorShape) {
+        if shape == self.shape {
+            return;
+        }
+
+        debug!("Change the cursor to {:?}", shape);
+        match shape {
+            CursorShape::Default => write!(self.terminal, "{}", DEFAULT_CURSOR_SHAPE),
+            CursorShape::Block => write!(self.terminal, "{}", cursor::SteadyBlock),
+            CursorShape::Bar => write!(self.terminal, "{}", cursor::SteadyBar),
+        }
+        .unwrap();
+        self.terminal.flush().unwrap();
+        self.shape = shape;
+    }
+
+    /// The terminal answers on stdin, see `Input::wait_for_cursor_pos`.
+    pub fn request_cursor_pos(&mut self) {
+        write!(self.terminal, "{}", REQUEST_CURSOR_POS).unwrap();
+        self.terminal.flush().unwrap();
+    }
+
+    /// The terminal answers on stdin, see `Input::wait_for_clipboard`.
+    pub fn request_clipboard(&mut self) {
+        write!(self.terminal, "{}", clipboard::REQUEST).unwrap();
+        self.terminal.flush().unwrap();
+    }
+
+    /// Follows the cursor over `text`, which the terminal wraps into the next rows.
+    fn advance(&mut self, text: &str) {
+        let (columns, height) = self.size;
+        let (x, rows) = text::advance(text, usize::from(self.cursor_pos.x), usize::from(columns));
+
+        // A full row leaves the cursor on its last column until the next character arrives,
+        // so move it to where the model expects it
+        if rows > 0 && x == 0 {
+            write!(self.terminal, "\r\n").unwrap();
         }
+
+        let y = u32::from(self.cursor_pos.y) + rows as u32;
+        self.cursor_pos.x = x as u16;
+        self.cursor_pos.y = y.min(u32::from(height)) as u16;
     }
 }
 
@@ -28,7 +161,7 @@ impl shell::Write for Terminal {
         debug!("Write {}", ch);
         write!(self.terminal, "{}", ch).unwrap();
 
-        self.cursor_pos.x += 1;
+        self.advance(ch.encode_utf8(&mut [0; 4]));
     }
 
     fn write_text(&mut self, text: &str) {
@@ -41,55 +174,98 @@ impl shell::Write for Terminal {
         )
         .unwrap();
 
-        self.cursor_pos.x += text.len() as u16;
+        self.advance(text);
     }
 }
 
 impl shell::Terminal for Terminal {
-    fn clear(&mut self) -> &mut shell::Clear {
+    fn clear(&mut self) -> &mut dyn shell::Clear {
         self
     }
 
-    fn cursor(&mut self) -> &mut shell::Cursor {
+    fn cursor(&mut self) -> &mut dyn shell::Cursor {
         self
     }
 
-    fn in_color(&mut self, color: Option<&color::Color>) -> &mut shell::Write {
+    fn in_color(&mut self, color: Option<&dyn color::Color>) -> &mut dyn shell::Write {
         if let Some(color) = color {
             debug!("With color");
-            write!(self.terminal, "{color}", color = color::Fg(color)).unwrap(); // TODO: Reset vor {color}?
+            write!(self.terminal, "{color}", color = color::Fg(color)).unwrap();
+        // TODO: Reset vor {color}?
         } else {
             debug!("Without color");
-            write!(self.terminal, "{reset}", reset = color::Fg(color::Reset)).unwrap(); // TODO: Reset vor {color}?
+            write!(self.terminal, "{reset}", reset = color::Fg(color::Reset)).unwrap();
+            // TODO: Reset vor {color}?
         }
 
         self
     }
 
-    fn newline(&mut self) -> &mut shell::Terminal {
+    fn newline(&mut self) -> &mut dyn shell::Terminal {
+        let (_, height) = self.size;
+
+        // On the last line the terminal scrolls and the cursor stays where it is
         self.cursor_pos.x = 0;
-        self.cursor_pos.y += 1;
+        self.cursor_pos.y = (self.cursor_pos.y + 1).min(height);
 
         let (x, y) = self.cursor_pos.get();
         debug!("Newline: x = {}, y = {}", x, y);
-        write!(self.terminal, "{}", cursor::Goto(x, y)).unwrap();
+        write!(self.terminal, "\r\n").unwrap();
 
         self
     }
 
-    fn flush(&mut self) -> &mut shell::Terminal {
+    fn flush(&mut self) -> &mut dyn shell::Terminal {
         debug!("Flush");
         self.terminal.flush().unwrap();
 
         self
     }
+
+    fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    fn copy_to_clipboard(&mut self, text: &str) {
+        debug!("Copy {:?} to the clipboard", text);
+        write!(self.terminal, "{}", clipboard::copy(text)).unwrap();
+        self.terminal.flush().unwrap();
+    }
+
+    /// Leaves raw mode, mouse reporting and bracketed paste, so a child gets the terminal as it
+    /// expects it.
+    fn suspend(&mut self) {
+        debug!("Suspend raw mode");
+        self.set_cursor_shape(CursorShape::Default);
+        write!(
+            self.terminal,
+            "{}{}",
+            EXIT_PASTE_SEQUENCE, EXIT_MOUSE_SEQUENCE
+        )
+        .unwrap();
+        self.terminal.flush().unwrap();
+        self.terminal.suspend().unwrap();
+    }
+
+    fn resume(&mut self) {
+        debug!("Resume raw mode");
+        self.terminal.resume().unwrap();
+        write!(
+            self.terminal,
+            "{}{}",
+            ENTER_MOUSE_SEQUENCE, ENTER_PASTE_SEQUENCE
+        )
+        .unwrap();
+        self.terminal.flush().unwrap();
+    }
 }
 
 impl shell::Clear for Terminal {
     fn all(&mut self) {
         debug!("Clear All!");
-        write!(self.terminal, "{}{}", clear::All, cursor::Goto(0, 1)).unwrap();
+        write!(self.terminal, "{}{}", clear::All, cursor::Goto(1, 1)).unwrap();
         self.cursor_pos = CursorPos::new();
+        self.renderer = Renderer::new();
     }
 
     fn line(&mut self) {
@@ -101,20 +277,20 @@ impl shell::Clear for Terminal {
             self.terminal,
             "{}{}",
             clear::CurrentLine,
-            cursor::Goto(x, y)
+            cursor::Goto(x + 1, y)
         )
         .unwrap();
     }
 }
 
 impl shell::Cursor for Terminal {
-    fn set_to(&mut self, x: u16, y: u16) -> &mut shell::Cursor {
+    fn set_to(&mut self, x: u16, y: u16) -> &mut dyn shell::Cursor {
         debug!("Set Cursor to x = {} y = {}", x, y);
 
         self.cursor_pos.x = x;
         self.cursor_pos.y = y;
 
-        //        write!(self.terminal, "{}", cursor::Goto(x, y)).unwrap(); // TODO: Use or drop?
+        write!(self.terminal, "{}", cursor::Goto(x + 1, y)).unwrap();
 
         self
     }
@@ -123,13 +299,13 @@ impl shell::Cursor for Terminal {
         self.cursor_pos.get()
     }
 
-    fn clear_after(&mut self) -> &mut shell::Cursor {
+    fn clear_after(&mut self) -> &mut dyn shell::Cursor {
         let (x, y) = self.cursor_pos.get();
         debug!("Clear after x = {}, y = {}", x, y);
         write!(
             self.terminal,
             "{}{}",
-            cursor::Goto(x, y),
+            cursor::Goto(x + 1, y),
             clear::AfterCursor
         )
         .unwrap();
@@ -137,7 +313,7 @@ impl shell::Cursor for Terminal {
         self
     }
 
-    fn set_x(&mut self, x: u16) -> &mut shell::Cursor {
+    fn set_x(&mut self, x: u16) -> &mut dyn shell::Cursor {
         debug!("Set x to {}", x);
 
         self.cursor_pos.x = x;
@@ -145,7 +321,7 @@ impl shell::Cursor for Terminal {
         self
     }
 
-    fn set_y(&mut self, y: u16) -> &mut shell::Cursor {
+    fn set_y(&mut self, y: u16) -> &mut dyn shell::Cursor {
         debug!("Set y to {}", y);
 
         self.cursor_pos.y = y;
@@ -153,7 +329,7 @@ impl shell::Cursor for Terminal {
         self
     }
 
-    fn move_left(&mut self, x: u16) -> &mut shell::Cursor {
+    fn move_left(&mut self, x: u16) -> &mut dyn shell::Cursor {
         debug!("Move Cursor {} left", x);
 
         if self.cursor_pos.x > x {
@@ -167,7 +343,7 @@ impl shell::Cursor for Terminal {
         self
     }
 
-    fn move_right(&mut self, x: u16) -> &mut shell::Cursor {
+    fn move_right(&mut self, x: u16) -> &mut dyn shell::Cursor {
         debug!("Move Cursor {} right", x);
 
         self.cursor_pos.x += x;
@@ -176,7 +352,7 @@ impl shell::Cursor for Terminal {
         self
     }
 
-    fn move_up(&mut self, y: u16) -> &mut shell::Cursor {
+    fn move_up(&mut self, y: u16) -> &mut dyn shell::Cursor {
         debug!("Move Cursor {} up", y);
 
         self.cursor_pos.y -= y;
@@ -185,7 +361,7 @@ impl shell::Cursor for Terminal {
         self
     }
 
-    fn move_down(&mut self, y: u16) -> &mut shell::Cursor {
+    fn move_down(&mut self, y: u16) -> &mut dyn shell::Cursor {
         debug!("Move Cursor {} down", y);
 
         self.cursor_pos.y += y;
@@ -193,4 +369,30 @@ impl shell::Cursor for Terminal {
 
         self
     }
+
+    fn save(&mut self) -> &mut dyn shell::Cursor {
+        debug!("Save Cursor");
+
+        self.saved_pos = self.cursor_pos;
+        write!(self.terminal, "{}", cursor::Save).unwrap();
+
+        self
+    }
+
+    fn restore(&mut self) -> &mut dyn shell::Cursor {
+        debug!("Restore Cursor");
+
+        self.cursor_pos = self.saved_pos;
+        write!(self.terminal, "{}", cursor::Restore).unwrap();
+
+        self
+    }
+}
+
+/// The shell which started us would get the brackets around its pastes otherwise.
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = write!(self.terminal, "{}", EXIT_PASTE_SEQUENCE);
+        let _ = self.terminal.flush();
+    }
 }
@@ -0,0 +1,971 @@
+use crate::my;
+use crate::shell::{self, Line as _};
+use log::debug;
+use std::ops::Range;
+use termion::event::Key;
+
+/// The class of a grapheme cluster, a word ends where the class changes. Big words, the
+/// `WORD`s of vi, only end at whitespace.
+fn class(grapheme: &str, big: bool) -> u8 {
+    match grapheme.chars().next() {
+        Some(ch) if ch.is_whitespace() => 0,
+        _ if big => 1,
+        Some(ch) if ch.is_alphanumeric() || ch == '_' => 2,
+        _ => 1,
+    }
+}
+
+fn next_word_start(graphemes: &[&str], index: usize, big: bool) -> usize {
+    let len = graphemes.len();
+    let mut index = index;
+    if index < len {
+        let current = class(graphemes[index], big);
+        while current != 0 && index < len && class(graphemes[index], big) == current {
+            index += 1;
+        }
+    }
+    while index < len && class(graphemes[index], big) == 0 {
+        index += 1;
+    }
+
+    index.min(len)
+}
+
+fn previous_word_start(graphemes: &[&str], index: usize, big: bool) -> usize {
+    let mut index = index;
+    while index > 0 && class(graphemes[index - 1], big) == 0 {
+        index -= 1;
+    }
+    if index > 0 {
+        let current = class(graphemes[index - 1], big);
+        while index > 0 && class(graphemes[index - 1], big) == current {
+            index -= 1;
+        }
+    }
+
+    index
+}
+
+/// The last grapheme cluster of the word at `index`.
+fn current_word_end(graphemes: &[&str], index: usize, big: bool) -> usize {
+    let len = graphemes.len();
+    if index >= len {
+        return len.saturating_sub(1);
+    }
+
+    let current = class(graphemes[index], big);
+    let mut index = index;
+    while index + 1 < len && class(graphemes[index + 1], big) == current {
+        index += 1;
+    }
+
+    index
+}
+
+/// The last grapheme cluster of the next word, even if `index` is inside of one.
+fn next_word_end(graphemes: &[&str], index: usize, big: bool) -> usize {
+    let len = graphemes.len();
+    let mut index = index + 1;
+    while index < len && class(graphemes[index], big) == 0 {
+        index += 1;
+    }
+
+    current_word_end(graphemes, index, big)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Find {
+    ch: char,
+    forward: bool,
+    /// Stops in front of the character, like `t` and `T`
+    till: bool,
+}
+
+impl Find {
+    /// Finds the `times`th `ch`. Repeating `t` starts behind the next character, which it
+    /// would find again otherwise.
+    fn locate(
+        &self,
+        graphemes: &[&str],
+        index: usize,
+        times: usize,
+        repeat: bool,
+    ) -> Option<usize> {
+        let ch = self.ch.to_string();
+        let skip = usize::from(repeat && self.till);
+        let matches = |index: &usize| graphemes[*index] == ch;
+
+        if self.forward {
+            let mut found = index + skip;
+            for _ in 0..times {
+                found = (found + 1..graphemes.len()).find(matches)?;
+            }
+            Some(if self.till { found - 1 } else { found })
+        } else {
+            let mut found = index.checked_sub(skip)?;
+            for _ in 0..times {
+                found = (0..found).rev().find(matches)?;
+            }
+            Some(if self.till { found + 1 } else { found })
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    WordForward { big: bool },
+    WordBackward { big: bool },
+    WordEnd { big: bool },
+    Start,
+    FirstNonBlank,
+    End,
+    Find(Find),
+    RepeatFind { reverse: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Motion(Motion),
+    /// A text object like `iw` or `a(`, by its opening character
+    Object {
+        inner: bool,
+        kind: char,
+    },
+    /// The whole line, like `dd`
+    Line,
+    /// The selection of the visual mode
+    Selection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Move(Motion),
+    Operate(Operator, Target),
+    /// Enters the insert mode, by the key `i`, `a`, `I` or `A`
+    Insert(char),
+    Replace(char),
+    Put {
+        before: bool,
+    },
+    Undo,
+    Repeat,
+    Visual,
+    /// Selects a text object in the visual mode
+    Select(Target),
+}
+
+impl Action {
+    /// Whether `.` repeats the action.
+    fn is_change(&self) -> bool {
+        match self {
+            Action::Operate(Operator::Yank, _) | Action::Operate(_, Target::Selection) => false,
+            Action::Operate(..) | Action::Insert(_) | Action::Replace(_) => true,
+            Action::Put { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+enum Parse<T> {
+    Incomplete,
+    Invalid,
+    Done(T),
+}
+
+impl<T> Parse<T> {
+    fn map<U>(self, f: impl FnOnce(T) -> U) -> Parse<U> {
+        match self {
+            Parse::Incomplete => Parse::Incomplete,
+            Parse::Invalid => Parse::Invalid,
+            Parse::Done(value) => Parse::Done(f(value)),
+        }
+    }
+}
+
+/// The largest count, as a longer one could keep the shell busy repeating for ages
+const MAX_COUNT: usize = 9999;
+
+/// Splits off a count like the `3` of `3w`. A leading `0` is the motion instead.
+fn take_count(keys: &[char]) -> (Option<usize>, &[char]) {
+    let digits = keys
+        .iter()
+        .enumerate()
+        .take_while(|(index, ch)| ch.is_ascii_digit() && (*index > 0 || **ch != '0'))
+        .count();
+    if digits == 0 {
+        return (None, keys);
+    }
+
+    let count: String = keys[..digits].iter().collect();
+    let count = count
+        .parse()
+        .map_or(MAX_COUNT, |count: usize| count.min(MAX_COUNT));
+    (Some(count), &keys[digits..])
+}
+
+fn parse_motion(keys: &[char]) -> Parse<Motion> {
+    let motion = match keys {
+        [] | ['f' | 'F' | 't' | 'T'] => return Parse::Incomplete,
+        ['h'] => Motion::Left,
+        ['l'] | [' '] => Motion::Right,
+        ['w'] => Motion::WordForward { big: false },
+        ['W'] => Motion::WordForward { big: true },
+        ['b'] => Motion::WordBackward { big: false },
+        ['B'] => Motion::WordBackward { big: true },
+        ['e'] => Motion::WordEnd { big: false },
+        ['E'] => Motion::WordEnd { big: true },
+        ['0'] => Motion::Start,
+        ['^'] => Motion::FirstNonBlank,
+        ['$'] => Motion::End,
+        [';'] => Motion::RepeatFind { reverse: false },
+        [','] => Motion::RepeatFind { reverse: true },
+        [key @ ('f' | 'F' | 't' | 'T'), ch] => Motion::Find(Find {
+            ch: *ch,
+            forward: key.is_lowercase(),
+            till: key.eq_ignore_ascii_case(&'t'),
+        }),
+        _ => return Parse::Invalid,
+    };
+
+    Parse::Done(motion)
+}
+
+fn parse_object(inner: bool, keys: &[char]) -> Parse<Target> {
+    let kind = match keys {
+        [] => return Parse::Incomplete,
+        [kind @ ('w' | 'W' | '"' | '\'' | '`' | '(' | '[' | '{' | '<')] => *kind,
+        [')' | 'b'] => '(',
+        [']'] => '[',
+        ['}' | 'B'] => '{',
+        ['>'] => '<',
+        _ => return Parse::Invalid,
+    };
+
+    Parse::Done(Target::Object { inner, kind })
+}
+
+/// Counts in front of an operator and its motion multiply, like `2d3w`.
+fn multiply(first: Option<usize>, second: Option<usize>) -> Option<usize> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(first.saturating_mul(second).min(MAX_COUNT)),
+        (first, second) => first.or(second),
+    }
+}
+
+/// Moves `times` steps from `index`, stopping early once a step stays in place.
+fn repeat_step(times: usize, index: usize, mut step: impl FnMut(usize) -> usize) -> usize {
+    let mut index = index;
+    for _ in 0..times {
+        let next = step(index);
+        if next == index {
+            break;
+        }
+        index = next;
+    }
+
+    index
+}
+
+/// Parses the keys of a command of the normal or visual mode, like `2dw` or `ci(`.
+fn parse(keys: &[char], visual: bool) -> Parse<(Option<usize>, Action)> {
+    let (count, rest) = take_count(keys);
+
+    let action = match rest {
+        [] => Parse::Incomplete,
+        [key @ ('d' | 'c' | 'y'), rest @ ..] => {
+            let operator = match key {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            if visual {
+                return match rest {
+                    [] => Parse::Done((count, Action::Operate(operator, Target::Selection))),
+                    _ => Parse::Invalid,
+                };
+            }
+
+            let (second, rest) = take_count(rest);
+            let target = match rest {
+                [] => Parse::Incomplete,
+                [repeated] if repeated == key => Parse::Done(Target::Line),
+                [kind @ ('i' | 'a'), rest @ ..] => parse_object(*kind == 'i', rest),
+                _ => parse_motion(rest).map(Target::Motion),
+            };
+
+            return target
+                .map(|target| (multiply(count, second), Action::Operate(operator, target)));
+        }
+        ['x'] if visual => Parse::Done(Action::Operate(Operator::Delete, Target::Selection)),
+        ['s'] if visual => Parse::Done(Action::Operate(Operator::Change, Target::Selection)),
+        [kind @ ('i' | 'a'), rest @ ..] if visual => {
+            parse_object(*kind == 'i', rest).map(Action::Select)
+        }
+        ['x'] => Parse::Done(Action::Operate(
+            Operator::Delete,
+            Target::Motion(Motion::Right),
+        )),
+        ['X'] => Parse::Done(Action::Operate(
+            Operator::Delete,
+            Target::Motion(Motion::Left),
+        )),
+        ['s'] => Parse::Done(Action::Operate(
+            Operator::Change,
+            Target::Motion(Motion::Right),
+        )),
+        ['S'] => Parse::Done(Action::Operate(Operator::Change, Target::Line)),
+        ['C'] => Parse::Done(Action::Operate(
+            Operator::Change,
+            Target::Motion(Motion::End),
+        )),
+        ['D'] => Parse::Done(Action::Operate(
+            Operator::Delete,
+            Target::Motion(Motion::End),
+        )),
+        ['Y'] => Parse::Done(Action::Operate(Operator::Yank, Target::Line)),
+        [key @ ('i' | 'a' | 'I' | 'A')] => Parse::Done(Action::Insert(*key)),
+        ['r'] => Parse::Incomplete,
+        ['r', ch] => Parse::Done(Action::Replace(*ch)),
+        ['p'] => Parse::Done(Action::Put { before: false }),
+        ['P'] => Parse::Done(Action::Put { before: true }),
+        ['u'] => Parse::Done(Action::Undo),
+        ['.'] => Parse::Done(Action::Repeat),
+        ['v'] => Parse::Done(Action::Visual),
+        _ => parse_motion(rest).map(Action::Move),
+    };
+
+    action.map(|action| (count, action))
+}
+
+/// The range of the text object `kind` around `index`, see `Target::Object`.
+fn object(graphemes: &[&str], index: usize, inner: bool, kind: char) -> Option<Range<usize>> {
+    let len = graphemes.len();
+    if len == 0 {
+        return None;
+    }
+
+    match kind {
+        'w' | 'W' => {
+            let big = kind == 'W';
+            let index = index.min(len - 1);
+            let current = class(graphemes[index], big);
+            let same = |index: usize| class(graphemes[index], big) == current;
+
+            let mut start = index;
+            while start > 0 && same(start - 1) {
+                start -= 1;
+            }
+            let mut end = index + 1;
+            while end < len && same(end) {
+                end += 1;
+            }
+
+            if !inner {
+                // A word takes the whitespace behind it, or in front of it at the end
+                let blank = |index: usize| class(graphemes[index], big) == 0;
+                let word_end = end;
+                while end < len && (current == 0) != blank(end) {
+                    end += 1;
+                }
+                if end == word_end && current != 0 {
+                    while start > 0 && blank(start - 1) {
+                        start -= 1;
+                    }
+                }
+            }
+
+            Some(start..end)
+        }
+        '"' | '\'' | '`' => {
+            let quote = kind.to_string();
+            let quotes: Vec<usize> = (0..len).filter(|i| graphemes[*i] == quote).collect();
+            let (open, close) = quotes
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|(_, close)| index <= *close)?;
+
+            Some(if inner {
+                open + 1..close
+            } else {
+                open..close + 1
+            })
+        }
+        _ => {
+            let (open, close) = match kind {
+                '(' => ("(", ")"),
+                '[' => ("[", "]"),
+                '{' => ("{", "}"),
+                _ => ("<", ">"),
+            };
+
+            // The innermost pair around the cursor, skipping the ones closed in between
+            let mut depth = 0;
+            let mut start = None;
+            for i in (0..=index.min(len - 1)).rev() {
+                if graphemes[i] == close && i != index {
+                    depth += 1;
+                } else if graphemes[i] == open {
+                    if depth == 0 {
+                        start = Some(i);
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let start = start?;
+
+            let mut depth = 0;
+            let mut end = None;
+            for (i, grapheme) in graphemes.iter().enumerate().skip(start + 1) {
+                if *grapheme == open {
+                    depth += 1;
+                } else if *grapheme == close {
+                    if depth == 0 {
+                        end = Some(i);
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let end = end?;
+
+            Some(if inner {
+                start + 1..end
+            } else {
+                start..end + 1
+            })
+        }
+    }
+}
+
+/// The last change and the keys typed in the insert mode it started, for `.`
+#[derive(Debug, Clone)]
+struct Change {
+    count: Option<usize>,
+    action: Action,
+    inserted: Vec<Key>,
+}
+
+/// Edits the line like vi, with a normal mode for commands, an insert mode for typing and a
+/// visual mode for selecting. Keys it does not handle go back to the shell, see `on_key`.
+pub struct Vi {
+    mode: shell::Mode,
+    /// The keys of the command being typed in the normal or visual mode
+    pending: Vec<char>,
+    /// Where the selection of the visual mode starts
+    anchor: usize,
+    last_find: Option<Find>,
+    last_change: Option<Change>,
+    /// Whether the keys typed in the insert mode belong to `last_change`
+    recording: bool,
+    replaying: bool,
+}
+
+impl Vi {
+    pub fn new() -> Self {
+        Self {
+            mode: shell::Mode::Insert,
+            pending: Vec::new(),
+            anchor: 0,
+            last_find: None,
+            last_change: None,
+            recording: false,
+            replaying: false,
+        }
+    }
+
+    pub fn mode(&self) -> shell::Mode {
+        self.mode
+    }
+
+    /// Starts a new line in the insert mode.
+    pub fn reset(&mut self) {
+        self.mode = shell::Mode::Insert;
+        self.pending.clear();
+        self.recording = false;
+    }
+
+    /// Selects from `anchor` to the cursor in the visual mode, or leaves it with `None`.
+    pub fn select(&mut self, anchor: Option<usize>) {
+        self.pending.clear();
+        match anchor {
+            Some(anchor) => {
+                self.mode = shell::Mode::Visual;
+                self.anchor = anchor;
+                self.recording = false;
+            }
+            None if self.mode == shell::Mode::Visual => self.mode = shell::Mode::Normal,
+            None => {}
+        }
+    }
+
+    /// The grapheme clusters selected in the visual mode.
+    pub fn selection(&self, line: &my::Line) -> Option<Range<usize>> {
+        if self.mode != shell::Mode::Visual {
+            return None;
+        }
+
+        let index = line.index();
+        let end = (self.anchor.max(index) + 1).min(line.graphemes().len());
+        Some(self.anchor.min(index)..end).filter(|range| !range.is_empty())
+    }
+
+    /// Handles `key` and returns the key the shell should handle instead, if any. In the normal
+    /// mode `k` and `j` become Up and Down.
+    pub fn on_key(&mut self, key: Key, line: &mut my::Line) -> Option<Key> {
+        let key = match self.mode {
+            shell::Mode::Insert => self.on_insert_key(key, line),
+            _ => self.on_normal_key(key, line),
+        };
+        self.fit_cursor(line);
+
+        key
+    }
+
+    /// Keeps the cursor on a character outside of the insert mode, like vi does.
+    pub fn fit_cursor(&self, line: &mut my::Line) {
+        let len = line.graphemes().len();
+        if self.mode != shell::Mode::Insert && len > 0 && line.index() >= len {
+            line.set_index(len - 1);
+        }
+    }
+
+    fn record(&mut self, key: Key) {
+        if let (true, Some(change)) = (self.recording, self.last_change.as_mut()) {
+            change.inserted.push(key);
+        }
+    }
+
+    fn on_insert_key(&mut self, key: Key, line: &mut my::Line) -> Option<Key> {
+        match key {
+            Key::Esc => {
+                self.leave_insert(line);
+                None
+            }
+            // Esc and the next key arrive together sometimes
+            Key::Alt(ch) => {
+                self.leave_insert(line);
+                self.on_normal_key(Key::Char(ch), line)
+            }
+            Key::Char(ch) if ch != '\n' => {
+                self.record(key);
+                line.insert(ch);
+                None
+            }
+            Key::Backspace => {
+                self.record(key);
+                line.remove_after();
+                None
+            }
+            _ => Some(key),
+        }
+    }
+
+    fn enter_insert(&mut self, line: &mut my::Line) {
+        debug!("Enter the insert mode");
+
+        line.begin_group();
+        self.mode = shell::Mode::Insert;
+    }
+
+    fn leave_insert(&mut self, line: &mut my::Line) {
+        debug!("Leave the insert mode");
+
+        line.end_group();
+        line.cursor().move_left();
+        self.mode = shell::Mode::Normal;
+        self.recording = false;
+    }
+
+    fn on_normal_key(&mut self, key: Key, line: &mut my::Line) -> Option<Key> {
+        let ch = match key {
+            Key::Char('\n') => {
+                self.pending.clear();
+                return Some(key);
+            }
+            Key::Char(ch) | Key::Alt(ch) => ch,
+            Key::Esc => {
+                self.pending.clear();
+                self.mode = shell::Mode::Normal;
+                return None;
+            }
+            Key::Backspace => return Some(Key::Left),
+            Key::Ctrl('r') => {
+                self.pending.clear();
+                line.redo();
+                return None;
+            }
+            _ => {
+                self.pending.clear();
+                return Some(key);
+            }
+        };
+
+        if self.pending.is_empty() {
+            match ch {
+                'k' => return Some(Key::Up),
+                'j' => return Some(Key::Down),
+                _ => {}
+            }
+        }
+
+        self.pending.push(ch);
+        match parse(&self.pending, self.mode == shell::Mode::Visual) {
+            Parse::Incomplete => {}
+            Parse::Invalid => {
+                debug!("Unknown vi command {:?}", self.pending);
+                self.pending.clear();
+            }
+            Parse::Done((count, action)) => {
+                self.pending.clear();
+                self.execute(count, action, line);
+            }
+        }
+
+        None
+    }
+
+    /// Where `motion` moves the cursor to `times` in a row, and whether an operator includes the
+    /// character there.
+    fn motion(
+        &mut self,
+        motion: Motion,
+        times: usize,
+        graphemes: &[&str],
+        index: usize,
+    ) -> Option<(usize, bool)> {
+        let len = graphemes.len();
+        let repeat = |index, step: fn(&[&str], usize, bool) -> usize, big| {
+            repeat_step(times, index, |index| step(graphemes, index, big))
+        };
+
+        let target = match motion {
+            Motion::Left => (index.saturating_sub(times), false),
+            Motion::Right => (index.saturating_add(times).min(len), false),
+            Motion::WordForward { big } => (repeat(index, next_word_start, big), false),
+            Motion::WordBackward { big } => (repeat(index, previous_word_start, big), false),
+            Motion::WordEnd { big } => (repeat(index, next_word_end, big), true),
+            Motion::Start => (0, false),
+            Motion::FirstNonBlank => {
+                let first = graphemes
+                    .iter()
+                    .position(|grapheme| class(grapheme, true) != 0);
+                (first.unwrap_or(len), false)
+            }
+            Motion::End => (len, false),
+            Motion::Find(find) => {
+                self.last_find = Some(find);
+                (find.locate(graphemes, index, times, false)?, find.forward)
+            }
+            Motion::RepeatFind { reverse } => {
+                let mut find = self.last_find?;
+                find.forward ^= reverse;
+                (find.locate(graphemes, index, times, true)?, find.forward)
+            }
+        };
+
+        Some(target)
+    }
+
+    fn range(
+        &mut self,
+        operator: Operator,
+        target: Target,
+        times: usize,
+        line: &my::Line,
+    ) -> Option<Range<usize>> {
+        let graphemes = line.graphemes();
+        let len = graphemes.len();
+        let index = line.index();
+
+        match target {
+            Target::Line => Some(0..len),
+            Target::Selection => {
+                let (start, end) = (self.anchor.min(index), self.anchor.max(index));
+                Some(start..(end + 1).min(len))
+            }
+            Target::Object { inner, kind } => object(&graphemes, index, inner, kind),
+            // `cw` changes up to the end of the word like `ce`, as vi has always done
+            Target::Motion(Motion::WordForward { big })
+                if operator == Operator::Change
+                    && index < len
+                    && class(graphemes[index], big) != 0 =>
+            {
+                let first = current_word_end(&graphemes, index, big);
+                let end = repeat_step(times.saturating_sub(1), first, |end| {
+                    next_word_end(&graphemes, end, big)
+                });
+                Some(index..(end + 1).min(len))
+            }
+            Target::Motion(motion) => {
+                let (to, inclusive) = self.motion(motion, times, &graphemes, index)?;
+                if to < index {
+                    Some(to..index)
+                } else if inclusive {
+                    Some(index..(to + 1).min(len))
+                } else {
+                    Some(index..to)
+                }
+            }
+        }
+    }
+
+    fn operate(&mut self, operator: Operator, target: Target, times: usize, line: &mut my::Line) {
+        let range = match self.range(operator, target, times, line) {
+            Some(range) => range,
+            None => return,
+        };
+        debug!("Apply {:?} to {:?}", operator, range);
+
+        self.mode = shell::Mode::Normal;
+        match operator {
+            Operator::Delete => line.kill(range),
+            Operator::Change => {
+                self.enter_insert(line);
+                line.kill(range);
+            }
+            Operator::Yank => {
+                line.copy(range.clone());
+                line.set_index(range.start);
+            }
+        }
+    }
+
+    fn execute(&mut self, count: Option<usize>, action: Action, line: &mut my::Line) {
+        debug!("Execute {:?} {:?} times", action, count);
+
+        let recorded = action.is_change() && !self.replaying;
+        if recorded {
+            self.last_change = Some(Change {
+                count,
+                action,
+                inserted: Vec::new(),
+            });
+        }
+        self.recording = recorded;
+
+        let times = count.unwrap_or(1);
+        let index = line.index();
+        match action {
+            Action::Move(motion) => {
+                let graphemes = line.graphemes();
+                if let Some((to, _)) = self.motion(motion, times, &graphemes, index) {
+                    line.set_index(to);
+                }
+            }
+            Action::Operate(operator, target) => self.operate(operator, target, times, line),
+            Action::Insert(key) => {
+                match key {
+                    'a' => line.cursor().move_right(),
+                    'I' => {
+                        let graphemes = line.graphemes();
+                        let first = self.motion(Motion::FirstNonBlank, 1, &graphemes, index);
+                        line.set_index(first.map_or(0, |(first, _)| first));
+                    }
+                    'A' => line.move_to_end(),
+                    _ => {}
+                }
+                self.enter_insert(line);
+            }
+            Action::Replace(ch) => {
+                let end = index.saturating_add(times);
+                if end <= line.graphemes().len() {
+                    line.replace(index..end, &ch.to_string().repeat(times));
+                    line.set_index(end - 1);
+                }
+            }
+            Action::Put { before } => {
+                let len = line.graphemes().len();
+                if !before && len > 0 {
+                    line.cursor().move_right();
+                }
+
+                line.begin_group();
+                for _ in 0..times {
+                    line.yank();
+                }
+                line.end_group();
+
+                // The cursor stays on the last character put
+                if line.graphemes().len() > len {
+                    line.cursor().move_left();
+                }
+            }
+            Action::Undo => {
+                for _ in 0..times {
+                    line.undo();
+                }
+            }
+            Action::Repeat => self.repeat(count, line),
+            Action::Visual if self.mode == shell::Mode::Visual => {
+                self.mode = shell::Mode::Normal;
+            }
+            Action::Visual => {
+                self.mode = shell::Mode::Visual;
+                self.anchor = index;
+            }
+            Action::Select(target) => {
+                if let Some(range) = self.range(Operator::Yank, target, times, line) {
+                    if !range.is_empty() {
+                        self.anchor = range.start;
+                        line.set_index(range.end - 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Repeats the last change, with `count` instead of its own if given.
+    fn repeat(&mut self, count: Option<usize>, line: &mut my::Line) {
+        let change = match self.last_change.clone() {
+            Some(change) => change,
+            None => return,
+        };
+
+        self.replaying = true;
+        self.execute(count.or(change.count), change.action, line);
+        if self.mode == shell::Mode::Insert {
+            for key in change.inserted {
+                self.on_insert_key(key, line);
+            }
+            self.leave_insert(line);
+        }
+        self.replaying = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    /// Types `keys` in the normal mode of a line with `text`, Esc written as `\x1b`.
+    fn edit(text: &str, keys: &str) -> (String, usize) {
+        let mut line = my::Line::new(&config::Line::default());
+        line.set_text(text);
+
+        let mut vi = Vi::new();
+        vi.on_key(Key::Esc, &mut line);
+        for ch in keys.chars() {
+            let key = if ch == '\x1b' {
+                Key::Esc
+            } else {
+                Key::Char(ch)
+            };
+            vi.on_key(key, &mut line);
+        }
+
+        (line.text(), line.index())
+    }
+
+    fn text_of(text: &str, keys: &str) -> String {
+        edit(text, keys).0
+    }
+
+    fn index(text: &str, keys: &str) -> usize {
+        edit(text, keys).1
+    }
+
+    #[test]
+    fn motions_move_the_cursor() {
+        let text = "echo foo-bar baz";
+
+        assert_eq!(index(text, ""), 15);
+        assert_eq!(index(text, "0w"), 5);
+        assert_eq!(index(text, "0ww"), 8);
+        assert_eq!(index(text, "0WW"), 13);
+        assert_eq!(index(text, "03w"), 9);
+        assert_eq!(index(text, "0e"), 3);
+        assert_eq!(index(text, "b"), 13);
+        assert_eq!(index(text, "0$"), 15);
+        assert_eq!(index(text, "0fz"), 15);
+        assert_eq!(index(text, "0tz"), 14);
+        assert_eq!(index(text, "0fa;"), 14);
+        assert_eq!(index(text, "0fa;,"), 10);
+        assert_eq!(index(text, "0l10l"), 11);
+    }
+
+    #[test]
+    fn operators_take_motions_and_counts() {
+        let text = "echo foo-bar baz";
+
+        assert_eq!(text_of(text, "0dw"), "foo-bar baz");
+        assert_eq!(text_of(text, "0d2w"), "-bar baz");
+        assert_eq!(text_of(text, "02dw"), "-bar baz");
+        assert_eq!(text_of(text, "0dfo"), " foo-bar baz");
+        assert_eq!(text_of(text, "0wD"), "echo ");
+        assert_eq!(text_of(text, "dd"), "");
+        assert_eq!(text_of(text, "03x"), "o foo-bar baz");
+        assert_eq!(text_of(text, "0cwprint\x1b"), "print foo-bar baz");
+        assert_eq!(index(text, "0cwprint\x1b"), 4);
+        assert_eq!(text_of(text, "0c$x"), "x");
+    }
+
+    #[test]
+    fn huge_counts_are_capped() {
+        let text = "echo foo-bar baz";
+
+        assert_eq!(take_count(&['9'; 20]).0, Some(MAX_COUNT));
+        assert_eq!(index(text, "099999999999999999999w"), 15);
+        assert_eq!(text_of(text, "099999999999999999999dw"), "");
+        assert_eq!(text_of(text, "$99999999b99999999x"), "");
+        assert_eq!(text_of(text, "0dw99999999999999u"), "");
+    }
+
+    #[test]
+    fn text_objects_select_around_the_cursor() {
+        assert_eq!(text_of("echo foo-bar baz", "0fbdiw"), "echo foo- baz");
+        assert_eq!(text_of("echo foo-bar baz", "0fbdaw"), "echo foo-baz");
+        assert_eq!(text_of("ls (a (b) c)", "0fbci(x\x1b"), "ls (a (x) c)");
+        assert_eq!(text_of("ls (a (b) c)", "0fadi("), "ls ()");
+        assert_eq!(text_of("ls (a (b) c)", "0fada)"), "ls ");
+        assert_eq!(text_of("echo \"hi there\"", "0ftdi\""), "echo \"\"");
+    }
+
+    #[test]
+    fn dot_repeats_the_last_change() {
+        assert_eq!(text_of("foo bar baz", "0cwX\x1bw."), "X X baz");
+        assert_eq!(text_of("abcdefg", "03x."), "g");
+        assert_eq!(text_of("abcdefg", "0x2."), "defg");
+    }
+
+    #[test]
+    fn undo_restores_whole_changes() {
+        assert_eq!(text_of("foo bar", "0dwu"), "foo bar");
+        assert_eq!(text_of("foo bar", "0cwX\x1bu"), "foo bar");
+        assert_eq!(text_of("foo bar", "0xxu"), "oo bar");
+        assert_eq!(text_of("foo bar", "0xx2u"), "foo bar");
+    }
+
+    #[test]
+    fn yanked_text_is_put_back() {
+        assert_eq!(edit("foo bar", "0dwP"), (String::from("foo bar"), 3));
+        assert_eq!(text_of("foo bar", "0ywP"), "foo foo bar");
+        assert_eq!(text_of("ab", "0xp"), "ba");
+        assert_eq!(text_of("abc", "0r-"), "-bc");
+        assert_eq!(text_of("abc", "02rx"), "xxc");
+    }
+
+    #[test]
+    fn visual_mode_selects_for_the_operators() {
+        assert_eq!(text_of("foo bar baz", "0wvlld"), "foo  baz");
+        assert_eq!(text_of("foo bar baz", "0wviwcX\x1b"), "foo X baz");
+        assert_eq!(text_of("foo bar baz", "0wve\x1bx"), "foo ba baz");
+    }
+
+    #[test]
+    fn insert_keys_enter_the_insert_mode() {
+        assert_eq!(text_of("bar", "Ifoo \x1bAbaz"), "foo barbaz");
+        assert_eq!(text_of("bar", "0a-"), "b-ar");
+        assert_eq!(text_of("bar", "0i-"), "-bar");
+    }
+}
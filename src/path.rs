@@ -1,99 +1,178 @@
 use crate::pool::Pool;
+use log::debug;
 use std::collections::HashMap;
-use std::ffi::OsString;
-use std::io;
+use std::env;
+use std::ffi::{CString, OsString};
+use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
 
-fn read_files<P: AsRef<Path>>(dir: P) -> io::Result<HashMap<String, PathBuf>> {
-    use std::fs;
-
-    let mut files = HashMap::new();
-    for entry in fs::read_dir(dir)? {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_file() {
-                let name = path
-                    .file_stem()
-                    .expect("Unable to stem filename")
-                    .to_str()
-                    .expect("Unable to stringify stem")
-                    .to_owned();
-                files.insert(name, path);
+/// A directory of PATH or a file in it the index had to leave out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(path: &Path, message: impl ToString) -> Self {
+        Self {
+            path: path.to_owned(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// Whether the user may run `path`, as the permissions of the file and its owner decide.
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    match CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => unsafe { libc::access(path.as_ptr(), libc::X_OK) == 0 },
+        Err(_) => false,
+    }
+}
+
+/// Returns the exact names of the executables in `dir`, following symlinks, and the problems
+/// which kept the directory or its files out.
+fn read_files(dir: &Path) -> (Vec<String>, Vec<Problem>) {
+    let mut files = Vec::new();
+    let mut problems = Vec::new();
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => return (files, vec![Problem::new(dir, e)]),
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                problems.push(Problem::new(dir, e));
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        // The metadata of the target, which a dangling link does not have
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                let message = match fs::read_link(&path) {
+                    Ok(target) => format!("dangling link to {}", target.display()),
+                    Err(_) => e.to_string(),
+                };
+                problems.push(Problem::new(&path, message));
+                continue;
             }
+        };
+
+        if !metadata.is_file() || !is_executable(&path) {
+            continue;
+        }
+
+        match entry.file_name().into_string() {
+            Ok(name) => files.push(name),
+            Err(name) => debug!("Skip {:?}, which is no valid UTF-8", name),
         }
     }
 
-    Ok(files)
+    (files, problems)
 }
 
+/// The executables of the directories in PATH. A name found in several directories keeps all
+/// of them in PATH order, so the first one is what runs and the others are shadowed by it.
 pub struct OsPath {
     path: Option<OsString>,
-    files: HashMap<String, PathBuf>,
+    files: HashMap<String, Vec<PathBuf>>,
+    problems: Vec<Problem>,
 }
 
 impl OsPath {
     pub fn load() -> Self {
-        use log::debug;
-        use std::env;
+        Self::with_path(env::var_os("PATH"))
+    }
+
+    /// Reads the directories of `path`, which is separated by colons like PATH.
+    pub fn with_path(path: Option<OsString>) -> Self {
         use std::sync::mpsc;
 
+        // An empty entry stands for the current directory
+        let dirs: Vec<PathBuf> = path
+            .iter()
+            .flat_map(env::split_paths)
+            .map(|dir| {
+                if dir.as_os_str().is_empty() {
+                    PathBuf::from(".")
+                } else {
+                    dir
+                }
+            })
+            .collect();
+
         let (tx, rx) = mpsc::channel();
         let mut pool = Pool::new(tx, rx);
-        let mut path_files = HashMap::new();
-        if let Some(path) = env::var_os("PATH") {
-            for path in path
-                .to_str()
-                .to_owned()
-                .expect("Unable to access PATH")
-                .split(':')
-            {
-                let path: String = path.to_owned();
-                pool.send(move |sender| {
-                    if let Ok(files) = read_files(&path) {
-                        sender.send(Some(files)).expect("Unable to send on channel");
-                    } else {
-                        sender.send(None).expect("Unable to send on channel");
-                    }
-                });
-
-                if let Some(Some(files)) = pool.receive() {
-                    path_files.extend(files.into_iter());
-                }
+        // The threads finish in any order, so every result keeps the index of its directory
+        let mut found: Vec<Option<(Vec<String>, Vec<Problem>)>> = vec![None; dirs.len()];
+        for (index, dir) in dirs.iter().enumerate() {
+            let dir = dir.clone();
+            pool.send(move |sender| {
+                let _ = sender.send((index, read_files(&dir)));
+            });
+
+            if let Some((index, listing)) = pool.receive() {
+                found[index] = Some(listing);
             }
+        }
+
+        debug!(
+            "Wait for {} running and {} waiting listings",
+            pool.get_pending(),
+            pool.get_waiting()
+        );
 
-            debug!(
-                "#1 Warte auf {} laufende und {} wartende Jobs",
-                pool.get_pending(),
-                pool.get_waiting()
-            );
+        while let Some((index, listing)) = pool.wait() {
+            found[index] = Some(listing);
+        }
 
-            while pool.is_running() {
-                if let Some(Some(files)) = pool.receive() {
-                    path_files.extend(files.into_iter());
+        let mut files: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        let mut problems = Vec::new();
+        for (dir, listing) in dirs.iter().zip(found) {
+            // A thread which panicked leaves no listing, the directory is left out then
+            let (names, found) = match listing {
+                Some(listing) => listing,
+                None => {
+                    problems.push(Problem::new(dir, "not listed"));
+                    continue;
                 }
+            };
 
-                debug!(
-                    "#2 Warte auf {} laufende und {} wartende Jobs",
-                    pool.get_pending(),
-                    pool.get_waiting()
-                );
+            for name in names {
+                let file = dir.join(&name);
+                files.entry(name).or_default().push(file);
             }
+            problems.extend(found);
+        }
+        debug!(
+            "Indexed {} executables with {} problems",
+            files.len(),
+            problems.len()
+        );
 
-            Self {
-                path: Some(path),
-                files: path_files,
-            }
-        } else {
-            Self {
-                path: None,
-                files: path_files,
-            }
+        Self {
+            path,
+            files,
+            problems,
         }
     }
 
     pub fn needs_refresh(&mut self) -> bool {
-        use std::env;
-
         env::var_os("PATH") != self.path
     }
 
@@ -108,4 +187,148 @@ impl OsPath {
     pub fn contains(&self, name: &str) -> bool {
         self.files.contains_key(name)
     }
+
+    /// The file which runs for `name`, the one of the first directory in PATH having it.
+    pub fn find(&self, name: &str) -> Option<&Path> {
+        self.find_all(name).first().map(PathBuf::as_path)
+    }
+
+    /// Every file named `name` in PATH order.
+    pub fn find_all(&self, name: &str) -> &[PathBuf] {
+        self.files.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The directories and files left out, in PATH order.
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    /// Creates the directories `dirs` with their executables in a new temporary directory.
+    fn create(test: &str, dirs: &[(&str, &[&str])]) -> PathBuf {
+        let root = env::temp_dir().join(format!("mysh-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        for (dir, files) in dirs {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            for file in files.iter() {
+                let file = root.join(dir).join(file);
+                fs::write(&file, "").unwrap();
+                fs::set_permissions(&file, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+
+        root
+    }
+
+    #[test]
+    fn names_are_kept_exactly() {
+        let root = create("names", &[("bin", &["python3.11", "foo.sh"])]);
+        let os_path = OsPath::with_path(Some(root.join("bin").into_os_string()));
+
+        assert!(os_path.contains("python3.11"));
+        assert!(os_path.contains("foo.sh"));
+        assert!(!os_path.contains("python3"));
+        assert!(!os_path.contains("foo"));
+        assert_eq!(os_path.count(), 2);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn earlier_directories_shadow_later_ones() {
+        let dirs: Vec<(String, &[&str])> = (0..12)
+            .map(|index| (format!("bin{}", index), &["ls", "cat"][..]))
+            .collect();
+        let dirs: Vec<(&str, &[&str])> = dirs
+            .iter()
+            .map(|(dir, files)| (dir.as_str(), *files))
+            .collect();
+        let root = create("order", &dirs);
+
+        let path = env::join_paths(dirs.iter().map(|(dir, _)| root.join(dir))).unwrap();
+        let os_path = OsPath::with_path(Some(path));
+
+        assert_eq!(os_path.find("ls"), Some(root.join("bin0/ls").as_path()));
+        let all: Vec<PathBuf> = (0..12)
+            .map(|index| root.join(format!("bin{}/cat", index)))
+            .collect();
+        assert_eq!(os_path.find_all("cat"), all.as_slice());
+        assert_eq!(os_path.find("missing"), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn broken_directories_keep_the_order_of_the_others() {
+        let root = create(
+            "broken",
+            &[("bin0", &["cat"]), ("bin3", &["ls", "cat"]), ("file", &[])],
+        );
+        let file = root.join("file").join("bin");
+        fs::write(&file, "").unwrap();
+
+        let path = env::join_paths([
+            root.join("bin0"),
+            root.join("missing"),
+            file.clone(),
+            root.join("bin3"),
+        ])
+        .unwrap();
+        let os_path = OsPath::with_path(Some(path));
+
+        assert_eq!(os_path.find("ls"), Some(root.join("bin3/ls").as_path()));
+        assert_eq!(
+            os_path.find_all("cat"),
+            [root.join("bin0/cat"), root.join("bin3/cat")]
+        );
+
+        let problems: Vec<&Path> = os_path
+            .problems()
+            .iter()
+            .map(|problem| problem.path.as_path())
+            .collect();
+        assert_eq!(problems, [root.join("missing"), file]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn only_executables_are_indexed() {
+        let root = create("executables", &[("bin", &["ls", "README"])]);
+        let bin = root.join("bin");
+        fs::set_permissions(bin.join("README"), fs::Permissions::from_mode(0o644)).unwrap();
+        fs::create_dir(bin.join("lib")).unwrap();
+        fs::set_permissions(bin.join("lib"), fs::Permissions::from_mode(0o755)).unwrap();
+        symlink(bin.join("ls"), bin.join("link")).unwrap();
+        symlink(bin.join("missing"), bin.join("gone")).unwrap();
+
+        let path = env::join_paths([bin.clone(), root.join("nowhere")]).unwrap();
+        let os_path = OsPath::with_path(Some(path));
+
+        assert!(os_path.contains("ls"));
+        assert!(os_path.contains("link"));
+        assert!(!os_path.contains("README"));
+        assert!(!os_path.contains("lib"));
+        assert!(!os_path.contains("gone"));
+
+        let problems: Vec<String> = os_path.problems().iter().map(Problem::to_string).collect();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(
+            problems[0],
+            format!(
+                "{}: dangling link to {}",
+                bin.join("gone").display(),
+                bin.join("missing").display()
+            )
+        );
+        assert!(problems[1].starts_with(&format!("{}: ", root.join("nowhere").display())));
+
+        fs::remove_dir_all(root).unwrap();
+    }
 }
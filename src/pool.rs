@@ -1,9 +1,16 @@
 use std::collections::VecDeque;
-use std::sync::mpsc::{Receiver, Sender};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
+
+/// How often `wait` looks for jobs which died without a result
+const REAP_INTERVAL: Duration = Duration::from_millis(10);
 
 struct Worker<T> {
-    closure: Box<Fn(Sender<T>) + Send + 'static>,
+    closure: Box<dyn Fn(Sender<T>) + Send + 'static>,
 }
 
 impl<T> Worker<T> {
@@ -18,6 +25,8 @@ pub struct Pool<T: Send + 'static> {
     queue: VecDeque<Worker<T>>,
     slots: usize,
     pending: usize,
+    /// The jobs which panicked and never send their result
+    panicked: Arc<AtomicUsize>,
     sender: Sender<T>,
     receiver: Receiver<T>,
 }
@@ -31,36 +40,73 @@ where
             queue: VecDeque::new(),
             slots: 8,
             pending: 0,
+            panicked: Arc::new(AtomicUsize::new(0)),
             sender,
             receiver,
         }
     }
 
+    fn spawn(&mut self, worker: Worker<T>) {
+        let tx = self.sender.clone();
+        let panicked = Arc::clone(&self.panicked);
+        thread::spawn(move || {
+            if panic::catch_unwind(AssertUnwindSafe(|| (worker.closure)(tx))).is_err() {
+                panicked.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        self.pending += 1;
+    }
+
     pub fn send(&mut self, closure: impl Fn(Sender<T>) + Send + 'static) {
         if (self.slots - self.pending) > 0 {
-            let tx = self.sender.clone();
-            thread::spawn(move || closure(tx));
-            self.pending += 1;
+            self.spawn(Worker::new(closure));
         } else {
             self.queue.push_back(Worker::new(closure));
         }
     }
 
     pub fn receive(&mut self) -> Option<T> {
-        if let Ok(result) = self.receiver.try_recv() {
-            self.pending -= 1;
-
-            for _ in 0..(self.slots - self.pending) {
-                if let Some(worker) = self.queue.pop_back() {
-                    let tx = self.sender.clone();
-                    thread::spawn(move || (worker.closure)(tx));
-                    self.pending += 1;
+        self.reap();
+        let result = self.receiver.try_recv().ok()?;
+        self.finish();
+
+        Some(result)
+    }
+
+    /// Blocks until the next result arrives, `None` once every job is done or died.
+    pub fn wait(&mut self) -> Option<T> {
+        loop {
+            self.reap();
+            if self.is_finished() {
+                return None;
+            }
+
+            match self.receiver.recv_timeout(REAP_INTERVAL) {
+                Ok(result) => {
+                    self.finish();
+                    return Some(result);
                 }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return None,
             }
+        }
+    }
 
-            Some(result)
-        } else {
-            None
+    /// Frees the slots of the jobs which panicked.
+    fn reap(&mut self) {
+        for _ in 0..self.panicked.swap(0, Ordering::SeqCst) {
+            self.finish();
+        }
+    }
+
+    /// Starts the waiting jobs which fit into the slot a finished one frees.
+    fn finish(&mut self) {
+        self.pending -= 1;
+
+        for _ in 0..(self.slots - self.pending) {
+            if let Some(worker) = self.queue.pop_back() {
+                self.spawn(worker);
+            }
         }
     }
 
@@ -80,3 +126,30 @@ where
         !self.is_finished()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn waiting_ends_when_jobs_die() {
+        let (tx, rx) = mpsc::channel();
+        let mut pool = Pool::new(tx, rx);
+        for index in 0..20 {
+            pool.send(move |sender| {
+                if index % 3 == 0 {
+                    panic!("job {} died", index);
+                }
+                sender.send(index).unwrap();
+            });
+        }
+
+        let mut results: Vec<usize> = std::iter::from_fn(|| pool.wait()).collect();
+        results.sort_unstable();
+
+        let expected: Vec<usize> = (0..20).filter(|index| index % 3 != 0).collect();
+        assert_eq!(results, expected);
+        assert!(pool.is_finished());
+    }
+}
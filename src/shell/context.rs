@@ -0,0 +1,49 @@
+use std::process::ExitStatus;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Code(i32),
+    Signal(i32),
+}
+
+impl Exit {
+    pub fn is_success(&self) -> bool {
+        *self == Exit::Code(0)
+    }
+}
+
+impl From<ExitStatus> for Exit {
+    fn from(status: ExitStatus) -> Self {
+        use std::os::unix::process::ExitStatusExt;
+
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Exit::Code(code),
+            (None, Some(signal)) => Exit::Signal(signal),
+            (None, None) => Exit::Code(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LastCommand {
+    pub exit: Exit,
+    pub duration: Duration,
+}
+
+/// How the line is edited, the vi modes included.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Mode {
+    #[default]
+    Emacs,
+    Insert,
+    Normal,
+    Visual,
+}
+
+/// The state of the shell the widgets are refreshed with.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub last_command: Option<LastCommand>,
+    pub mode: Mode,
+}
@@ -9,8 +9,43 @@ pub trait XCursor {
 pub trait Line: Drawable {
     fn remove_after(&mut self);
     fn remove_before(&mut self);
-    fn cursor(&mut self) -> &mut XCursor;
+    fn cursor(&mut self) -> &mut dyn XCursor;
     fn insert(&mut self, ch: char);
     fn reset(&mut self);
-    fn set_padding(&mut self, cursor: &shell::Cursor);
+    /// Replaces the text and puts the cursor behind it.
+    fn set_text(&mut self, text: &str);
+    /// Moves the cursor into the previous row of a wrapped line, if there is one.
+    fn move_up(&mut self) -> bool;
+    /// Moves the cursor into the next row of a wrapped line, if there is one.
+    fn move_down(&mut self) -> bool;
+    fn move_to_start(&mut self);
+    fn move_to_end(&mut self);
+    /// Moves the cursor to the start of the word in front of it.
+    fn move_word_left(&mut self);
+    /// Moves the cursor behind the end of the word it is in or in front of.
+    fn move_word_right(&mut self);
+    /// Removes the text up to the start of the word in front of the cursor, where words are
+    /// separated by whitespace.
+    fn kill_word_before(&mut self);
+    /// Removes the text up to the end of the word the cursor is in or in front of.
+    fn kill_word_after(&mut self);
+    /// Removes the text from the cursor to the end.
+    fn kill_to_end(&mut self);
+    /// Removes the text from the start to the cursor.
+    fn kill_to_start(&mut self);
+    /// Inserts the text removed last by one of the kills.
+    fn yank(&mut self);
+    /// Replaces the text just yanked with the one killed before it, going round the kills.
+    fn yank_pop(&mut self);
+    /// Swaps the character in front of the cursor with the one under it and moves on, or the
+    /// last two characters at the end.
+    fn transpose(&mut self);
+    /// Restores the text and the cursor from before the last change.
+    fn undo(&mut self);
+    /// Makes the last undone change again.
+    fn redo(&mut self);
+    fn text(&self) -> String;
+    fn set_padding(&mut self, cursor: &dyn shell::Cursor);
+    /// The first column behind the text
+    fn end(&self) -> u16;
 }
@@ -1,12 +1,17 @@
+pub mod context;
 pub mod line;
 pub mod prompt;
+pub mod template;
 pub mod terminal;
 pub mod widget;
 
+pub use self::context::{Context, Exit, LastCommand, Mode};
 pub use self::line::Line;
 pub use self::prompt::Prompt;
 pub use self::terminal::{Clear, Cursor, CursorPos, Terminal, Write};
-pub use self::widget::{Caret, Location, User, Widget};
+pub use self::widget::{
+    Caret, Duration, Environment, Git, Host, Location, Ssh, Status, Time, User, Venv, Widget,
+};
 
 pub trait Shell {
     fn clear(&mut self);
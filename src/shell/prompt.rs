@@ -1,32 +1,449 @@
 use crate::drawable::Drawable;
-use crate::{config, shell};
+use crate::pool::Pool;
+use crate::shell::template::Template;
+use crate::{config, shell, text};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::sync::mpsc;
+
+const WIDGETS: [&str; 11] = [
+    "ssh", "user", "host", "dir", "git", "venv", "env", "time", "duration", "status", "caret",
+];
+const DEFAULT_WIDGETS: [&str; 6] = ["user", "dir", "git", "duration", "status", "caret"];
+const DEFAULT_SEPARATOR: &str = " ";
+
+fn default_separator(name: &str) -> &'static str {
+    match name {
+        "host" => " at ",
+        "dir" => " in ",
+        "git" => " on ",
+        "caret" => "",
+        _ => DEFAULT_SEPARATOR,
+    }
+}
+
+fn or_default<T: Clone + Default>(config: &Option<T>, fallback: bool) -> Option<T> {
+    match config {
+        Some(config) => Some(config.clone()),
+        None if fallback => Some(T::default()),
+        None => None,
+    }
+}
+
+/// Creates the widget `name`. Optional widgets without a config are only created with `fallback`.
+fn create_widget(
+    name: &str,
+    config: &config::Prompt,
+    fallback: bool,
+) -> Option<Box<dyn shell::Widget>> {
+    let widget: Box<dyn shell::Widget> = match name {
+        "user" => Box::new(shell::User::new(&or_default(&config.user, fallback)?)),
+        "host" => Box::new(shell::Host::new(&or_default(&config.host, fallback)?)),
+        "time" => Box::new(shell::Time::new(&or_default(&config.time, fallback)?)),
+        "venv" => Box::new(shell::Venv::new(&or_default(&config.venv, fallback)?)),
+        "env" => Box::new(shell::Environment::new(&or_default(&config.env, fallback)?)),
+        "ssh" => Box::new(shell::Ssh::new(&or_default(&config.ssh, fallback)?)),
+        "dir" => Box::new(shell::Location::new(&config.location)),
+        "git" => Box::new(shell::Git::new(&or_default(&config.git, fallback)?)),
+        "status" => Box::new(shell::Status::new(&or_default(&config.status, fallback)?)),
+        "duration" => Box::new(shell::Duration::new(&or_default(
+            &config.duration,
+            fallback,
+        )?)),
+        "caret" => Box::new(shell::Caret::new(&config.caret)),
+        _ => {
+            warn!("Unknown prompt widget {}", name);
+            return None;
+        }
+    };
+
+    Some(widget)
+}
+
+/// The widgets shown on the left and whether they fall back to their default config. By
+/// default only the configured widgets not asked for on the right are shown, otherwise
+/// everything asked for works.
+fn requested(config: &config::Prompt, format: bool) -> (Vec<&str>, bool) {
+    match (format, &config.widgets) {
+        (true, _) => (WIDGETS.to_vec(), true),
+        (false, Some(names)) => (names.iter().map(String::as_str).collect(), true),
+        (false, None) => {
+            let right = config.right.as_deref().unwrap_or_default();
+            let names = DEFAULT_WIDGETS
+                .iter()
+                .filter(|name| !right.iter().any(|right| right == *name))
+                .copied()
+                .collect();
+            (names, false)
+        }
+    }
+}
+
+/// The widgets `config` creates with their default config if it has none of its own.
+pub fn fallback_widgets(config: &config::Prompt) -> Vec<&str> {
+    let format = config
+        .format
+        .as_ref()
+        .is_some_and(|format| Template::parse(format).is_ok());
+    let (mut names, fallback) = requested(config, format);
+    if !fallback {
+        names.clear();
+    }
+    names.extend(config.right.iter().flatten().map(String::as_str));
+
+    names
+}
+
+enum Layout {
+    Format(Template),
+    Widgets(Vec<String>),
+}
 
 pub struct Prompt {
-    widgets: Vec<Box<shell::Widget>>,
+    widgets: Vec<Box<dyn shell::Widget>>,
+    layout: Layout,
+    right: Vec<String>,
+    transient: Option<shell::Caret>,
+    separators: HashMap<String, String>,
+    pool: Pool<(usize, usize, String)>,
+    generation: usize,
 }
 
 impl Prompt {
     pub fn new(config: &config::Prompt) -> Self {
-        let mut widgets: Vec<Box<shell::Widget>> = Vec::new();
+        let format = config.format.as_ref().and_then(|format| {
+            Template::parse(format)
+                .map_err(|e| warn!("Invalid prompt format {:?}: {}", format, e))
+                .ok()
+        });
+
+        let (names, fallback) = requested(config, format.is_some());
+
+        let mut widgets: Vec<_> = names
+            .iter()
+            .filter_map(|name| create_widget(name, config, fallback))
+            .collect();
+
+        let layout = match format {
+            Some(template) => Layout::Format(template),
+            None => Layout::Widgets(
+                names
+                    .iter()
+                    .filter(|name| widgets.iter().any(|widget| widget.name() == **name))
+                    .map(|name| (*name).to_owned())
+                    .collect(),
+            ),
+        };
 
-        let user = if let Some(ref user) = config.user {
-            Some(shell::User::new(user))
+        // Widgets asked for on the right are always created, but a name shares one widget
+        let right = config.right.clone().unwrap_or_default();
+        for name in right.iter() {
+            if !widgets.iter().any(|widget| widget.name() == name) {
+                widgets.extend(create_widget(name, config, true));
+            }
+        }
+
+        let transient = if config.transient {
+            let mut caret = config.caret.clone();
+            caret.on_newline = false;
+            Some(shell::Caret::new(&caret))
         } else {
             None
         };
 
-        widgets.push(Box::new(shell::Location { user }));
-        let widget = shell::Caret::new(&config.caret);
-        widgets.push(Box::new(widget));
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            widgets,
+            layout,
+            right,
+            transient,
+            separators: config.separators.clone(),
+            pool: Pool::new(tx, rx),
+            generation: 0,
+        }
+    }
+
+    fn separator(&self, name: &str) -> &str {
+        self.separators
+            .get(name)
+            .map(String::as_str)
+            .unwrap_or_else(|| default_separator(name))
+    }
+
+    /// The widgets of `names` with something to show, each with the separator in front of it.
+    fn visible<'a>(
+        &'a self,
+        names: &'a [String],
+    ) -> impl Iterator<Item = (&'a str, &'a dyn shell::Widget)> + 'a {
+        names
+            .iter()
+            .filter_map(move |name| {
+                let widget = self.widgets.iter().find(|widget| widget.name() == name)?;
+                if widget.should_render() && !widget.text().is_empty() {
+                    Some((name, widget.as_ref()))
+                } else {
+                    None
+                }
+            })
+            .enumerate()
+            .map(move |(index, (name, widget))| {
+                let separator = if index == 0 { "" } else { self.separator(name) };
+                (separator, widget)
+            })
+    }
+
+    fn render_widgets(&self, names: &[String], term: &mut dyn shell::Terminal) {
+        for (separator, widget) in self.visible(names) {
+            if !separator.is_empty() {
+                term.in_color(None).write_text(separator);
+            }
+
+            widget.render_on(term);
+        }
+    }
+
+    /// Draws the right widgets at the end of the cursor row, unless the text up to `end` reaches them.
+    /// The cursor stays where it is.
+    pub fn render_right_on(&self, term: &mut dyn shell::Terminal, end: u16) {
+        let width: usize = self
+            .visible(&self.right)
+            .map(|(separator, widget)| text::width(separator) + text::width(&widget.text()))
+            .sum();
+        if width == 0 {
+            return;
+        }
+
+        let (columns, _) = term.size();
+        let start = usize::from(columns).saturating_sub(width);
+        if usize::from(end) >= start {
+            return;
+        }
+
+        let (_, y) = term.cursor().get();
+        term.cursor().save().set_to(start as u16, y);
+        self.render_widgets(&self.right, term);
+        term.cursor().restore();
+    }
+
+    /// The caret a submitted command keeps, if the prompt is transient.
+    pub fn transient(&self) -> Option<&shell::Caret> {
+        self.transient.as_ref()
+    }
+
+    pub fn refresh(&mut self, context: &shell::Context) {
+        self.generation += 1;
+
+        if let Some(caret) = self.transient.as_mut() {
+            shell::Widget::refresh(caret, context);
+        }
+
+        for (index, widget) in self.widgets.iter_mut().enumerate() {
+            widget.refresh(context);
+            if !widget.should_render() {
+                continue;
+            }
 
-        Self { widgets }
+            if let Some(job) = widget.job() {
+                let generation = self.generation;
+                self.pool.send(move |sender| {
+                    // Nobody waits for the result once the prompt is dropped
+                    let _ = sender.send((generation, index, job()));
+                });
+            }
+        }
+    }
+
+    /// Refreshes only the carets, e.g. for a new vi mode, keeping what the other widgets show.
+    pub fn refresh_caret(&mut self, context: &shell::Context) {
+        if let Some(caret) = self.transient.as_mut() {
+            shell::Widget::refresh(caret, context);
+        }
+
+        for widget in self.widgets.iter_mut() {
+            if widget.name() == "caret" {
+                widget.refresh(context);
+            }
+        }
+    }
+
+    /// Hands the finished jobs to their widgets and returns whether the prompt needs a repaint.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Some((generation, index, result)) = self.pool.receive() {
+            // Results of an earlier prompt are outdated
+            if generation == self.generation {
+                debug!("Update widget {} of the prompt", self.widgets[index].name());
+                self.widgets[index].update(result);
+                changed = true;
+            }
+        }
+
+        changed
     }
 }
 
 impl Drawable for Prompt {
-    fn render_on(&self, term: &mut shell::Terminal) {
-        for widget in self.widgets.iter() {
-            widget.render_on(term);
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        match self.layout {
+            Layout::Format(ref template) => template.render_on(&self.widgets, term),
+            Layout::Widgets(ref names) => self.render_widgets(names, term),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTerminal;
+    use crate::my::screen::Pen;
+    use std::time;
+    use termion::color::Rgb;
+
+    fn prompt(config: &str) -> Prompt {
+        Prompt::new(&toml::from_str(config).unwrap())
+    }
+
+    fn after(code: i32, millis: u64) -> shell::Context {
+        shell::Context {
+            last_command: Some(shell::LastCommand {
+                exit: shell::Exit::Code(code),
+                duration: time::Duration::from_millis(millis),
+            }),
+            ..shell::Context::default()
+        }
+    }
+
+    #[test]
+    fn widgets_are_joined_by_their_separators() {
+        let mut prompt = prompt(
+            r#"
+            widgets = ["duration", "status", "caret"]
+            caret = { user = "> ", admin = "> " }
+            "#,
+        );
+        prompt.refresh(&after(2, 4200));
+
+        let mut term = MockTerminal::new(40, 5);
+        prompt.render_on(&mut term);
+
+        assert_eq!(term.lines(), ["took 4.2s 2>"]);
+        assert_eq!(term.cursor_cell(), (13, 0));
+    }
+
+    #[test]
+    fn hidden_widgets_leave_no_separator() {
+        let mut prompt = prompt(
+            r#"
+            widgets = ["duration", "status", "caret"]
+            caret = { user = "> ", admin = "> " }
+            "#,
+        );
+        prompt.refresh(&after(0, 10));
+
+        let mut term = MockTerminal::new(40, 5);
+        prompt.render_on(&mut term);
+
+        assert_eq!(term.lines(), [">"]);
+        assert_eq!(term.cursor_cell(), (2, 0));
+    }
+
+    #[test]
+    fn format_colors_the_placeholders() {
+        let mut prompt = prompt(
+            r#"
+            format = "[<#ff0000>{caret}</>]"
+            caret = { user = "$", admin = "$" }
+            "#,
+        );
+        prompt.refresh(&shell::Context::default());
+
+        let mut term = MockTerminal::new(40, 5);
+        prompt.render_on(&mut term);
+
+        assert_eq!(term.lines(), ["[$]"]);
+        assert_eq!(term.pen_at(1, 0), Some(Pen::new(&Rgb(255, 0, 0))));
+        assert_eq!(term.pen_at(2, 0), None);
+    }
+
+    #[test]
+    fn a_theme_colors_widgets_without_a_config() {
+        let theme: crate::theme::Theme =
+            toml::from_str(include_str!("../../themes/gruvbox.toml")).unwrap();
+        for prompt_config in [
+            r#"widgets = ["status", "caret"]"#,
+            r#"format = "{status}{caret}""#,
+        ] {
+            let mut config: config::Config = toml::from_str(&format!(
+                "[prompt]\n{}\n[line]\ncapacity = 100\n[colorize]",
+                prompt_config
+            ))
+            .unwrap();
+            theme.apply_to(&mut config);
+
+            let mut prompt = Prompt::new(&config.prompt);
+            prompt.refresh(&after(1, 10));
+            let mut term = MockTerminal::new(20, 5);
+            prompt.render_on(&mut term);
+
+            assert!(term.lines()[0].starts_with('1'), "{}", prompt_config);
+            assert_eq!(term.pen_at(0, 0), Some(Pen::new(&Rgb(251, 73, 52))));
+        }
+    }
+
+    #[test]
+    fn widgets_on_the_right_are_not_repeated_on_the_left() {
+        let mut prompt = prompt(
+            r#"
+            right = ["status"]
+            caret = { user = "> ", admin = "> " }
+            status = { symbol = "x" }
+            "#,
+        );
+        prompt.refresh(&after(1, 10));
+
+        let mut term = MockTerminal::new(80, 5);
+        prompt.render_on(&mut term);
+
+        assert!(term.lines()[0].ends_with('>'));
+        assert!(!term.lines()[0].contains("x1"));
+    }
+
+    #[test]
+    fn right_widgets_end_at_the_last_column() {
+        let mut prompt = prompt(
+            r#"
+            widgets = ["caret"]
+            right = ["status"]
+            caret = { user = "> ", admin = "> " }
+            status = { symbol = "x" }
+            "#,
+        );
+        prompt.refresh(&after(1, 10));
+
+        let mut term = MockTerminal::new(20, 5);
+        prompt.render_on(&mut term);
+        prompt.render_right_on(&mut term, 2);
+
+        assert_eq!(term.lines(), [">                 x1"]);
+        assert_eq!(term.cursor_cell(), (2, 0));
+    }
+
+    #[test]
+    fn right_widgets_give_way_to_a_long_line() {
+        let mut prompt = prompt(
+            r#"
+            widgets = ["caret"]
+            right = ["status"]
+            caret = { user = "> ", admin = "> " }
+            "#,
+        );
+        prompt.refresh(&after(1, 10));
+
+        let mut term = MockTerminal::new(20, 5);
+        prompt.render_on(&mut term);
+        prompt.render_right_on(&mut term, 19);
+
+        assert_eq!(term.lines(), [">"]);
+    }
+}
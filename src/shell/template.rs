@@ -0,0 +1,333 @@
+use crate::shell::{self, Widget};
+use std::iter::Peekable;
+use std::str::Chars;
+use termion::color::{AnsiValue, Color, Rgb};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Style {
+    Rgb(Rgb),
+    Ansi(AnsiValue),
+}
+
+impl Style {
+    fn parse(name: &str) -> Option<Self> {
+        let hex = name.strip_prefix('#').unwrap_or_default();
+        if hex.len() == 6 && hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            let channel = |from| u8::from_str_radix(&hex[from..from + 2], 16).ok();
+
+            return Some(Style::Rgb(Rgb(channel(0)?, channel(2)?, channel(4)?)));
+        }
+
+        let ansi = match name {
+            "black" => 0,
+            "red" => 1,
+            "green" => 2,
+            "yellow" => 3,
+            "blue" => 4,
+            "magenta" => 5,
+            "cyan" => 6,
+            "white" => 7,
+            "light-black" => 8,
+            "light-red" => 9,
+            "light-green" => 10,
+            "light-yellow" => 11,
+            "light-blue" => 12,
+            "light-magenta" => 13,
+            "light-cyan" => 14,
+            "light-white" => 15,
+            _ => return None,
+        };
+
+        Some(Style::Ansi(AnsiValue(ansi)))
+    }
+
+    fn color(&self) -> &dyn Color {
+        match self {
+            Style::Rgb(color) => color,
+            Style::Ansi(color) => color,
+        }
+    }
+}
+
+// AnsiValue of termion cannot be compared
+impl PartialEq for Style {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Style::Rgb(color), Style::Rgb(other)) => color == other,
+            (Style::Ansi(color), Style::Ansi(other)) => color.0 == other.0,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Text(String),
+    Newline,
+    Placeholder(String),
+    Conditional(String, Vec<Node>),
+    Styled(Style, Vec<Node>),
+}
+
+/// A parsed prompt format string.
+///
+/// - `{name}` renders the widget registered under `name`
+/// - `{?name:...}` renders `...` only if the widget `name` has something to show
+/// - `<#rrggbb>...</>` or `<green>...</>` renders `...` in the given color
+/// - `\{`, `\}`, `\<`, `\>` and `\\` escape the special characters
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+#[derive(PartialEq)]
+enum End {
+    Eof,
+    Brace,
+    Tag,
+}
+
+impl<'a> Parser<'a> {
+    fn new(format: &'a str) -> Self {
+        Self {
+            chars: format.chars().peekable(),
+        }
+    }
+
+    fn parse(&mut self, end: End) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            let ch = match self.chars.next() {
+                Some(ch) => ch,
+                None if end == End::Eof => break,
+                None if end == End::Brace => return Err(String::from("Missing closing }")),
+                None => return Err(String::from("Missing closing </>")),
+            };
+
+            match ch {
+                '\\' => match self.chars.next() {
+                    Some(ch) => text.push(ch),
+                    None => return Err(String::from("Dangling \\ at the end")),
+                },
+                '\r' => {}
+                '\n' => {
+                    Self::push_text(&mut nodes, &mut text);
+                    nodes.push(Node::Newline);
+                }
+                '}' if end == End::Brace => break,
+                '}' => return Err(String::from("Unexpected }")),
+                '{' => {
+                    Self::push_text(&mut nodes, &mut text);
+                    nodes.push(self.parse_placeholder()?);
+                }
+                '<' if self.chars.peek() == Some(&'/') => {
+                    self.chars.next();
+                    if self.chars.next() != Some('>') {
+                        return Err(String::from("Expected </>"));
+                    }
+
+                    if end != End::Tag {
+                        return Err(String::from("Unexpected </>"));
+                    }
+
+                    break;
+                }
+                '<' => {
+                    Self::push_text(&mut nodes, &mut text);
+                    nodes.push(self.parse_style()?);
+                }
+                _ => text.push(ch),
+            }
+        }
+
+        Self::push_text(&mut nodes, &mut text);
+
+        Ok(nodes)
+    }
+
+    fn push_text(nodes: &mut Vec<Node>, text: &mut String) {
+        if !text.is_empty() {
+            nodes.push(Node::Text(text.split_off(0)));
+        }
+    }
+
+    fn read_until(&mut self, delimiter: char) -> Result<String, String> {
+        let mut name = String::new();
+        for ch in self.chars.by_ref() {
+            if ch == delimiter {
+                return Ok(name);
+            }
+
+            name.push(ch);
+        }
+
+        Err(format!("Unterminated {}", name))
+    }
+
+    fn parse_placeholder(&mut self) -> Result<Node, String> {
+        if self.chars.peek() == Some(&'?') {
+            self.chars.next();
+
+            let name = self.read_until(':')?;
+            let nodes = self.parse(End::Brace)?;
+
+            Ok(Node::Conditional(name.trim().to_owned(), nodes))
+        } else {
+            let name = self.read_until('}')?;
+
+            Ok(Node::Placeholder(name.trim().to_owned()))
+        }
+    }
+
+    fn parse_style(&mut self) -> Result<Node, String> {
+        let name = self.read_until('>')?;
+        let style = Style::parse(name.trim()).ok_or_else(|| format!("Unknown color {}", name))?;
+        let nodes = self.parse(End::Tag)?;
+
+        Ok(Node::Styled(style, nodes))
+    }
+}
+
+impl Template {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        let nodes = Parser::new(format).parse(End::Eof)?;
+
+        Ok(Self { nodes })
+    }
+
+    pub fn render_on(&self, widgets: &[Box<dyn Widget>], term: &mut dyn shell::Terminal) {
+        Self::render_nodes(&self.nodes, None, widgets, term);
+    }
+
+    fn find<'a>(widgets: &'a [Box<dyn Widget>], name: &str) -> Option<&'a dyn Widget> {
+        widgets
+            .iter()
+            .find(|widget| widget.name() == name && widget.should_render())
+            .map(|widget| widget.as_ref())
+    }
+
+    fn render_nodes(
+        nodes: &[Node],
+        style: Option<&Style>,
+        widgets: &[Box<dyn Widget>],
+        term: &mut dyn shell::Terminal,
+    ) {
+        for node in nodes.iter() {
+            match node {
+                Node::Text(text) => {
+                    term.in_color(style.map(Style::color)).write_text(text);
+                }
+                Node::Newline => {
+                    term.newline();
+                }
+                Node::Placeholder(name) => match (Self::find(widgets, name), style) {
+                    (Some(widget), Some(style)) => {
+                        term.in_color(Some(style.color()))
+                            .write_text(&widget.text());
+                    }
+                    (Some(widget), None) => widget.render_on(term),
+                    (None, _) => {}
+                },
+                Node::Conditional(name, nodes) => {
+                    let is_empty = Self::find(widgets, name)
+                        .map(|widget| widget.text().is_empty())
+                        .unwrap_or(true);
+                    if !is_empty {
+                        Self::render_nodes(nodes, style, widgets, term);
+                    }
+                }
+                Node::Styled(style, nodes) => {
+                    Self::render_nodes(nodes, Some(style), widgets, term);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(format: &str) -> Result<Vec<Node>, String> {
+        Template::parse(format).map(|template| template.nodes)
+    }
+
+    fn text(text: &str) -> Node {
+        Node::Text(text.to_owned())
+    }
+
+    #[test]
+    fn placeholders_and_text_are_split() {
+        assert_eq!(
+            parse("{ user } in {dir}\r\n$ ").unwrap(),
+            [
+                Node::Placeholder(String::from("user")),
+                text(" in "),
+                Node::Placeholder(String::from("dir")),
+                Node::Newline,
+                text("$ "),
+            ]
+        );
+        assert_eq!(parse("").unwrap(), []);
+    }
+
+    #[test]
+    fn escapes_keep_special_characters() {
+        assert_eq!(
+            parse(r"\{user\} \<b\> \\").unwrap(),
+            [text(r"{user} <b> \")]
+        );
+    }
+
+    #[test]
+    fn conditionals_and_tags_nest() {
+        assert_eq!(
+            parse("{?host: at <#ff8000>{host}</>}<green>$</>").unwrap(),
+            [
+                Node::Conditional(
+                    String::from("host"),
+                    vec![
+                        text(" at "),
+                        Node::Styled(
+                            Style::Rgb(Rgb(255, 128, 0)),
+                            vec![Node::Placeholder(String::from("host"))]
+                        ),
+                    ]
+                ),
+                Node::Styled(Style::Ansi(AnsiValue(2)), vec![text("$")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn colors_are_names_or_hex() {
+        assert_eq!(Style::parse("#00FFaa"), Some(Style::Rgb(Rgb(0, 255, 170))));
+        assert_eq!(Style::parse("light-red"), Some(Style::Ansi(AnsiValue(9))));
+        assert_eq!(Style::parse("#00ffa"), None);
+        assert_eq!(Style::parse("#00ffag"), None);
+        assert_eq!(Style::parse("#+1ffaa"), None);
+        assert_eq!(Style::parse("#1ü234"), None);
+        assert_eq!(Style::parse("pink"), None);
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let error = |format| parse(format).unwrap_err();
+
+        assert_eq!(error("{user"), "Unterminated user");
+        assert_eq!(error("{?host: at"), "Missing closing }");
+        assert_eq!(error("user}"), "Unexpected }");
+        assert_eq!(error("<red>$"), "Missing closing </>");
+        assert_eq!(error("$</>"), "Unexpected </>");
+        assert_eq!(error("<red>$</x"), "Expected </>");
+        assert_eq!(error("<red$"), "Unterminated red$");
+        assert_eq!(error("$\\"), "Dangling \\ at the end");
+        assert_eq!(error("<#1ü234>$</>"), "Unknown color #1ü234");
+    }
+}
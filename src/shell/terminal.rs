@@ -6,15 +6,17 @@ pub trait Clear {
 }
 
 pub trait Cursor {
-    fn set_to(&mut self, x: u16, y: u16) -> &mut Cursor;
+    fn set_to(&mut self, x: u16, y: u16) -> &mut dyn Cursor;
     fn get(&self) -> (u16, u16);
-    fn clear_after(&mut self) -> &mut Cursor;
-    fn set_x(&mut self, x: u16) -> &mut Cursor;
-    fn set_y(&mut self, y: u16) -> &mut Cursor;
-    fn move_left(&mut self, x: u16) -> &mut Cursor;
-    fn move_right(&mut self, x: u16) -> &mut Cursor;
-    fn move_up(&mut self, y: u16) -> &mut Cursor;
-    fn move_down(&mut self, y: u16) -> &mut Cursor;
+    fn clear_after(&mut self) -> &mut dyn Cursor;
+    fn set_x(&mut self, x: u16) -> &mut dyn Cursor;
+    fn set_y(&mut self, y: u16) -> &mut dyn Cursor;
+    fn move_left(&mut self, x: u16) -> &mut dyn Cursor;
+    fn move_right(&mut self, x: u16) -> &mut dyn Cursor;
+    fn move_up(&mut self, y: u16) -> &mut dyn Cursor;
+    fn move_down(&mut self, y: u16) -> &mut dyn Cursor;
+    fn save(&mut self) -> &mut dyn Cursor;
+    fn restore(&mut self) -> &mut dyn Cursor;
 }
 
 pub trait Write {
@@ -23,13 +25,22 @@ pub trait Write {
 }
 
 pub trait Terminal {
-    fn clear(&mut self) -> &mut Clear;
-    fn cursor(&mut self) -> &mut Cursor;
-    fn in_color(&mut self, color: Option<&color::Color>) -> &mut Write;
-    fn newline(&mut self) -> &mut Terminal;
-    fn flush(&mut self) -> &mut Terminal;
+    fn clear(&mut self) -> &mut dyn Clear;
+    fn cursor(&mut self) -> &mut dyn Cursor;
+    fn in_color(&mut self, color: Option<&dyn color::Color>) -> &mut dyn Write;
+    fn newline(&mut self) -> &mut dyn Terminal;
+    fn flush(&mut self) -> &mut dyn Terminal;
+    /// The number of columns and rows
+    fn size(&self) -> (u16, u16);
+    /// Puts `text` into the clipboard of the terminal, see `clipboard`.
+    fn copy_to_clipboard(&mut self, text: &str);
+    /// Leaves raw mode for a child, like the editor of the user.
+    fn suspend(&mut self);
+    fn resume(&mut self);
 }
 
+/// Columns count from 0 and rows from 1.
+#[derive(Debug, Clone, Copy)]
 pub struct CursorPos {
     pub x: u16,
     pub y: u16,
@@ -1,9 +1,26 @@
 use crate::drawable::Drawable;
-use crate::{config, shell};
+use crate::{config, git, shell, signal, system, text};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 use termion::color::{self, Rgb};
 
+pub type Job = Box<dyn Fn() -> String + Send>;
+
 pub trait Widget: Drawable {
+    fn name(&self) -> &str;
     fn should_render(&self) -> bool;
+    fn text(&self) -> String;
+
+    /// Called before the prompt is rendered, so expensive state is not gathered on every `text`
+    fn refresh(&mut self, _context: &shell::Context) {}
+
+    /// Slow work that runs on the worker pool after `refresh`. Its result is handed to `update`,
+    /// until then the widget is rendered with a placeholder.
+    fn job(&self) -> Option<Job> {
+        None
+    }
+
+    fn update(&mut self, _result: String) {}
 }
 
 pub struct User {
@@ -19,57 +36,637 @@ impl User {
 }
 
 impl Widget for User {
+    fn name(&self) -> &str {
+        "user"
+    }
+
     fn should_render(&self) -> bool {
         self.config.render
     }
+
+    fn text(&self) -> String {
+        whoami::username()
+    }
 }
 
 impl Drawable for User {
-    fn render_on(&self, term: &mut shell::Terminal) {
-        let config::Rgb(r, g, b) = self.config.color;
-        let text = whoami::username();
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
+    }
+}
 
-        term.in_color(Some(&Rgb(r, g, b))).write_text(&text);
+pub struct Host {
+    pub config: config::Host,
+    fqdn: Option<String>,
+}
+
+impl Host {
+    pub fn new(config: &config::Host) -> Self {
+        Self {
+            config: config.clone(),
+            fqdn: None,
+        }
+    }
+}
+
+impl Widget for Host {
+    fn name(&self) -> &str {
+        "host"
+    }
+
+    fn should_render(&self) -> bool {
+        self.config.render
+    }
+
+    fn text(&self) -> String {
+        let hostname = whoami::hostname();
+
+        match self.fqdn {
+            Some(ref fqdn) => fqdn.to_owned(),
+            // Until the FQDN is resolved the short name serves as placeholder
+            None => hostname.split('.').next().unwrap_or_default().to_owned(),
+        }
+    }
+
+    fn job(&self) -> Option<Job> {
+        if !self.config.fqdn || self.fqdn.is_some() {
+            return None;
+        }
+
+        Some(Box::new(|| {
+            let hostname = whoami::hostname();
+            system::fqdn(&hostname).unwrap_or(hostname)
+        }))
+    }
+
+    fn update(&mut self, fqdn: String) {
+        self.fqdn = Some(fqdn);
+    }
+}
+
+impl Drawable for Host {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
+    }
+}
+
+pub struct Time {
+    pub config: config::Time,
+    time: String,
+}
+
+impl Time {
+    pub fn new(config: &config::Time) -> Self {
+        Self {
+            config: config.clone(),
+            time: String::new(),
+        }
+    }
+}
+
+impl Widget for Time {
+    fn name(&self) -> &str {
+        "time"
+    }
+
+    fn should_render(&self) -> bool {
+        self.config.render
+    }
+
+    fn text(&self) -> String {
+        self.time.to_owned()
+    }
+
+    fn refresh(&mut self, _context: &shell::Context) {
+        self.time = system::local_time(&self.config.format);
+    }
+}
+
+impl Drawable for Time {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
+    }
+}
+
+pub struct Venv {
+    pub config: config::Venv,
+}
+
+impl Venv {
+    pub fn new(config: &config::Venv) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+}
+
+impl Widget for Venv {
+    fn name(&self) -> &str {
+        "venv"
+    }
+
+    fn should_render(&self) -> bool {
+        self.config.render
+    }
+
+    fn text(&self) -> String {
+        use std::env;
+
+        if let Some(venv) = env::var_os("VIRTUAL_ENV") {
+            let venv = PathBuf::from(venv);
+            venv.file_name()
+                .unwrap_or_else(|| venv.as_os_str())
+                .to_string_lossy()
+                .into_owned()
+        } else {
+            env::var("CONDA_DEFAULT_ENV").unwrap_or_default()
+        }
+    }
+}
+
+impl Drawable for Venv {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
+    }
+}
+
+pub struct Environment {
+    pub config: config::Environment,
+}
+
+impl Environment {
+    pub fn new(config: &config::Environment) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+}
+
+impl Widget for Environment {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn should_render(&self) -> bool {
+        self.config.render
+    }
+
+    fn text(&self) -> String {
+        use std::env;
+
+        let mut markers = Vec::new();
+        if env::var_os("IN_NIX_SHELL").is_some() {
+            markers.push(self.config.nix.as_str());
+        }
+        if env::var_os("DIRENV_DIR").is_some() {
+            markers.push(self.config.direnv.as_str());
+        }
+
+        markers.join(" ")
+    }
+}
+
+impl Drawable for Environment {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
+    }
+}
+
+pub struct Ssh {
+    pub config: config::Ssh,
+}
+
+impl Ssh {
+    pub fn new(config: &config::Ssh) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+}
+
+impl Widget for Ssh {
+    fn name(&self) -> &str {
+        "ssh"
+    }
+
+    fn should_render(&self) -> bool {
+        self.config.render
+    }
+
+    fn text(&self) -> String {
+        use std::env;
+
+        let is_remote = ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+            .iter()
+            .any(|name| env::var_os(name).is_some());
+
+        if is_remote {
+            self.config.symbol.to_owned()
+        } else {
+            String::new()
+        }
+    }
+}
+
+impl Drawable for Ssh {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
     }
 }
 
 pub struct Location {
-    pub user: Option<User>,
+    pub config: config::Location,
+    dir: String,
+}
+
+impl Location {
+    pub fn new(config: &config::Location) -> Self {
+        Self {
+            config: config.clone(),
+            dir: String::new(),
+        }
+    }
+
+    /// The text for `dir`, with `home` as the directory `~` stands for.
+    fn shorten(&self, dir: &Path, home: Option<&Path>) -> String {
+        use std::path::Component;
+
+        let root = if self.config.repo_root {
+            git::root(dir)
+        } else {
+            None
+        };
+
+        // The part in front of the components, which is never abbreviated
+        let (mut prefix, rest) = match (root, home) {
+            (Some(root), _) => {
+                let name = root.file_name().map(display_name).unwrap_or_default();
+                (name, dir.strip_prefix(root).unwrap_or(dir))
+            }
+            (None, Some(home)) if self.config.tilde && dir.starts_with(home) => {
+                (String::from("~"), dir.strip_prefix(home).unwrap_or(dir))
+            }
+            (None, _) => (String::new(), dir),
+        };
+
+        let mut components: Vec<String> = rest
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(display_name(name)),
+                _ => None,
+            })
+            .collect();
+
+        if let Some(count) = self.config.components {
+            if components.len() > count {
+                components.drain(..components.len() - count);
+                prefix = self.config.ellipsis.clone();
+            }
+        }
+
+        if self.config.abbreviate {
+            let last = components.len().saturating_sub(1);
+            for component in components.iter_mut().take(last) {
+                *component = abbreviate(component);
+            }
+        }
+
+        let mut text = prefix;
+        if dir.has_root() && text.is_empty() {
+            text.push('/');
+            text += &components.join("/");
+        } else {
+            for component in components.iter() {
+                text.push('/');
+                text += component;
+            }
+        }
+
+        match self.config.max_width {
+            Some(width) if text::width(&text) > width => {
+                let width = width.saturating_sub(text::width(&self.config.ellipsis));
+                self.config.ellipsis.clone() + text::last_columns(&text, width)
+            }
+            _ => text,
+        }
+    }
+}
+
+/// Shows the bytes of a name which are no UTF-8 as `\xNN` instead of replacing them.
+fn display_name(name: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut bytes = name.as_bytes();
+    let mut text = String::new();
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(valid) => {
+                text += valid;
+                return text;
+            }
+            Err(e) => {
+                let (valid, invalid) = bytes.split_at(e.valid_up_to());
+                text += std::str::from_utf8(valid).unwrap_or_default();
+
+                let len = e.error_len().unwrap_or(invalid.len());
+                for byte in &invalid[..len] {
+                    text += &format!("\\x{:02x}", byte);
+                }
+                bytes = &invalid[len..];
+            }
+        }
+    }
+}
+
+/// Keeps the first character, and the one after it for hidden directories.
+fn abbreviate(name: &str) -> String {
+    let len = if name.starts_with('.') { 2 } else { 1 };
+
+    text::graphemes(name).take(len).collect()
 }
 
 impl Widget for Location {
+    fn name(&self) -> &str {
+        "dir"
+    }
+
     fn should_render(&self) -> bool {
         true
     }
+
+    fn text(&self) -> String {
+        self.dir.to_owned()
+    }
+
+    fn refresh(&mut self, _context: &shell::Context) {
+        use std::env;
+
+        let home = env::var_os("HOME").map(PathBuf::from);
+        self.dir = env::current_dir()
+            .map(|dir| self.shorten(&dir, home.as_deref()))
+            .unwrap_or_default();
+    }
 }
 
 impl Drawable for Location {
-    fn render_on(&self, term: &mut shell::Terminal) {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let cur_dir = self.text();
+
+        if let Some(config::Rgb(r, g, b)) = self.config.color {
+            term.in_color(Some(&Rgb(r, g, b))).write_text(&cur_dir);
+        } else {
+            term.in_color(Some(&color::LightGreen)).write_text(&cur_dir);
+        }
+    }
+}
+
+fn describe_status(config: &config::Git, status: &git::Status) -> String {
+    let mut counts = String::new();
+    if status.ahead > 0 {
+        counts += &format!("{}{}", config.ahead, status.ahead);
+    }
+    if status.behind > 0 {
+        counts += &format!("{}{}", config.behind, status.behind);
+    }
+
+    let mut markers = String::new();
+    if status.staged {
+        markers += &config.staged;
+    }
+    if status.dirty {
+        markers += &config.dirty;
+    }
+    if status.untracked {
+        markers += &config.untracked;
+    }
+
+    vec![counts, markers]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub struct Git {
+    pub config: config::Git,
+    dir: Option<PathBuf>,
+    head: Option<git::Head>,
+    status: Option<String>,
+}
+
+impl Git {
+    pub fn new(config: &config::Git) -> Self {
+        Self {
+            config: config.clone(),
+            dir: None,
+            head: None,
+            status: None,
+        }
+    }
+}
+
+impl Widget for Git {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn should_render(&self) -> bool {
+        self.config.render
+    }
+
+    fn text(&self) -> String {
+        let mut text = match self.head {
+            Some(git::Head::Branch(ref name)) => name.to_owned(),
+            Some(git::Head::Detached(ref commit)) => format!("({})", commit),
+            None => return String::new(),
+        };
+
+        let status = self.status.as_ref().unwrap_or(&self.config.placeholder);
+        if !status.is_empty() {
+            text.push(' ');
+            text.push_str(status);
+        }
+
+        text
+    }
+
+    fn refresh(&mut self, _context: &shell::Context) {
         use std::env;
 
-        let cur_dir = env::current_dir()
-            .map(|dir| dir.as_os_str().to_string_lossy().to_string())
-            .unwrap();
+        if !self.config.render {
+            return;
+        }
+
+        self.dir = env::current_dir().ok();
+        self.head = self.dir.as_ref().and_then(|dir| git::head(dir));
+        self.status = None;
+    }
+
+    fn job(&self) -> Option<Job> {
+        self.head.as_ref()?;
+
+        let dir = self.dir.clone()?;
+        let config = self.config.clone();
+
+        Some(Box::new(move || {
+            let timeout = std::time::Duration::from_millis(config.timeout);
+
+            git::status(&dir, timeout)
+                .map(|status| describe_status(&config, &status))
+                .unwrap_or_default()
+        }))
+    }
+
+    fn update(&mut self, status: String) {
+        self.status = Some(status);
+    }
+}
+
+impl Drawable for Git {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
+    }
+}
+
+pub struct Status {
+    pub config: config::Status,
+    exit: Option<shell::Exit>,
+}
+
+impl Status {
+    pub fn new(config: &config::Status) -> Self {
+        Self {
+            config: config.clone(),
+            exit: None,
+        }
+    }
+}
+
+impl Widget for Status {
+    fn name(&self) -> &str {
+        "status"
+    }
+
+    fn should_render(&self) -> bool {
+        let failed = self.exit.map(|exit| !exit.is_success()).unwrap_or(false);
+
+        self.config.render && failed
+    }
+
+    fn text(&self) -> String {
+        match self.exit {
+            Some(shell::Exit::Code(code)) => format!("{}{}", self.config.symbol, code),
+            Some(shell::Exit::Signal(number)) => match signal::name(number) {
+                Some(name) => format!("{}{} {}", self.config.symbol, 128 + number, name),
+                None => format!("{}{}", self.config.symbol, 128 + number),
+            },
+            None => String::new(),
+        }
+    }
+
+    fn refresh(&mut self, context: &shell::Context) {
+        self.exit = context.last_command.as_ref().map(|command| command.exit);
+    }
+}
+
+impl Drawable for Status {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or(config::Rgb(205, 0, 0));
+
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
+    }
+}
+
+fn format_duration(duration: std::time::Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else if seconds < 60 * 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {}m", seconds / (60 * 60), seconds % (60 * 60) / 60)
+    }
+}
+
+pub struct Duration {
+    pub config: config::Duration,
+    duration: Option<std::time::Duration>,
+}
+
+impl Duration {
+    pub fn new(config: &config::Duration) -> Self {
+        Self {
+            config: config.clone(),
+            duration: None,
+        }
+    }
+}
+
+impl Widget for Duration {
+    fn name(&self) -> &str {
+        "duration"
+    }
+
+    fn should_render(&self) -> bool {
+        let threshold = std::time::Duration::from_millis(self.config.threshold);
+        let exceeded = self
+            .duration
+            .map(|duration| duration >= threshold)
+            .unwrap_or(false);
 
-        if let Some(ref user) = self.user {
-            user.render_on(term);
+        self.config.render && exceeded
+    }
+
+    fn text(&self) -> String {
+        match self.duration {
+            Some(duration) => format!("{}{}", self.config.prefix, format_duration(duration)),
+            None => String::new(),
         }
+    }
+
+    fn refresh(&mut self, context: &shell::Context) {
+        self.duration = context
+            .last_command
+            .as_ref()
+            .map(|command| command.duration);
+    }
+}
+
+impl Drawable for Duration {
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = self.config.color.unwrap_or_default();
 
-        term.in_color(None).write_text(" in ");
-        term.in_color(Some(&color::LightGreen)).write_text(&cur_dir);
+        term.in_color(Some(&Rgb(r, g, b))).write_text(&self.text());
     }
 }
 
 pub struct Caret {
     pub config: config::Caret,
+    /// Looked up once, since a group can take a lookup over the network
     pub is_admin: bool,
+    pub mode: shell::Mode,
 }
 
 impl Caret {
     pub fn new(config: &config::Caret) -> Self {
         Self {
             config: config.clone(),
-            is_admin: false,
+            is_admin: system::is_admin(config.admin_group.as_deref()),
+            mode: shell::Mode::default(),
         }
     }
 
@@ -79,19 +676,35 @@ impl Caret {
 }
 
 impl Widget for Caret {
+    fn name(&self) -> &str {
+        "caret"
+    }
+
     fn should_render(&self) -> bool {
         self.config.render
     }
+
+    fn text(&self) -> String {
+        match (self.mode, &self.config.normal) {
+            (shell::Mode::Normal | shell::Mode::Visual, Some(normal)) => normal.to_owned(),
+            _ if self.is_admin => self.config.admin.to_owned(),
+            _ => self.config.user.to_owned(),
+        }
+    }
+
+    fn refresh(&mut self, context: &shell::Context) {
+        self.mode = context.mode;
+    }
 }
 
 impl Drawable for Caret {
-    fn render_on(&self, term: &mut shell::Terminal) {
-        let config::Rgb(r, g, b) = self.config.color;
-        let text = if self.is_admin {
-            self.config.admin.to_owned()
+    fn render_on(&self, term: &mut dyn shell::Terminal) {
+        let config::Rgb(r, g, b) = if self.is_admin {
+            self.config.admin_color.unwrap_or(config::Rgb(205, 0, 0))
         } else {
-            self.config.user.to_owned()
+            self.config.color.unwrap_or_default()
         };
+        let text = self.text();
 
         if self.is_on_newline() {
             term.newline()
@@ -102,3 +715,79 @@ impl Drawable for Caret {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: &str = "/home/mysh";
+
+    fn shorten(config: config::Location, dir: &str) -> String {
+        Location::new(&config).shorten(Path::new(dir), Some(Path::new(HOME)))
+    }
+
+    #[test]
+    fn the_home_directory_becomes_a_tilde() {
+        let config = config::Location::default;
+
+        assert_eq!(shorten(config(), "/home/mysh/src/mysh"), "~/src/mysh");
+        assert_eq!(shorten(config(), "/home/mysh"), "~");
+        assert_eq!(shorten(config(), "/home/myshell"), "/home/myshell");
+        assert_eq!(shorten(config(), "/etc"), "/etc");
+        assert_eq!(shorten(config(), "/"), "/");
+
+        let config = config::Location {
+            tilde: false,
+            ..config()
+        };
+        assert_eq!(shorten(config, "/home/mysh/src"), "/home/mysh/src");
+    }
+
+    #[test]
+    fn only_the_last_components_are_kept() {
+        let config = || config::Location {
+            components: Some(2),
+            ..config::Location::default()
+        };
+
+        assert_eq!(
+            shorten(config(), "/home/mysh/src/mysh/target"),
+            "…/mysh/target"
+        );
+        assert_eq!(shorten(config(), "/usr/local/bin"), "…/local/bin");
+        assert_eq!(shorten(config(), "/home/mysh/src"), "~/src");
+        assert_eq!(shorten(config(), "/usr/bin"), "/usr/bin");
+
+        let config = config::Location {
+            abbreviate: true,
+            ..config()
+        };
+        assert_eq!(shorten(config, "/home/mysh/src/.config/mysh"), "…/.c/mysh");
+    }
+
+    #[test]
+    fn parents_are_abbreviated() {
+        let config = config::Location {
+            abbreviate: true,
+            ..config::Location::default()
+        };
+
+        assert_eq!(shorten(config.clone(), "/home/mysh/src/mysh"), "~/s/mysh");
+        assert_eq!(shorten(config, "/usr/local/bin"), "/u/l/bin");
+    }
+
+    #[test]
+    fn wide_paths_are_cut_from_the_front() {
+        let config = config::Location {
+            max_width: Some(8),
+            ..config::Location::default()
+        };
+
+        assert_eq!(
+            shorten(config.clone(), "/home/mysh/src/mysh/target"),
+            "…/target"
+        );
+        assert_eq!(shorten(config.clone(), "/home/mysh/src"), "~/src");
+        assert_eq!(shorten(config, "/usr/bin"), "/usr/bin");
+    }
+}
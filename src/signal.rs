@@ -0,0 +1,82 @@
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::{mem, ptr};
+
+/// A handler that does nothing. Unlike an ignored signal it is reset by `exec`, so children still
+/// receive the signal.
+extern "C" fn ignore(_: libc::c_int) {}
+
+/// Keeps Ctrl-C and Ctrl-\ for a running child from terminating the shell as well.
+pub fn catch_interrupts() {
+    let handler = ignore as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGQUIT, handler);
+    }
+}
+
+/// The end of the pipe `notify_resize` writes to, or -1 while nobody watches.
+static RESIZE_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn notify_resize(_: libc::c_int) {
+    let fd = RESIZE_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // Only async-signal-safe calls are allowed here. A full pipe already has a pending resize.
+        unsafe {
+            libc::write(fd, b"w".as_ptr() as *const libc::c_void, 1);
+        }
+    }
+}
+
+/// Reports terminal resizes on the returned file descriptor, which gets readable on SIGWINCH.
+pub fn watch_resize() -> Option<RawFd> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } != 0 {
+        return None;
+    }
+    RESIZE_PIPE.store(fds[1], Ordering::Relaxed);
+
+    let handler = notify_resize as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGWINCH, &action, ptr::null_mut());
+    }
+
+    Some(fds[0])
+}
+
+pub fn name(signal: i32) -> Option<&'static str> {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGCHLD => "SIGCHLD",
+        libc::SIGCONT => "SIGCONT",
+        libc::SIGSTOP => "SIGSTOP",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGTTIN => "SIGTTIN",
+        libc::SIGTTOU => "SIGTTOU",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGSYS => "SIGSYS",
+        _ => return None,
+    };
+
+    Some(name)
+}
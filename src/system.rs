@@ -0,0 +1,92 @@
+use std::ffi::{CStr, CString};
+use std::{mem, ptr};
+
+/// Resolves the fully qualified name of `host`. This may ask a DNS server and take a while.
+pub fn fqdn(host: &str) -> Option<String> {
+    let node = CString::new(host).ok()?;
+
+    unsafe {
+        let mut hints: libc::addrinfo = mem::zeroed();
+        hints.ai_flags = libc::AI_CANONNAME;
+
+        let mut info = ptr::null_mut();
+        if libc::getaddrinfo(node.as_ptr(), ptr::null(), &hints, &mut info) != 0 {
+            return None;
+        }
+
+        let name = (*info).ai_canonname;
+        let fqdn = if name.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+        };
+        libc::freeaddrinfo(info);
+
+        fqdn
+    }
+}
+
+/// Formats the current local time with the strftime `format`.
+pub fn local_time(format: &str) -> String {
+    let format = match CString::new(format) {
+        Ok(format) => format,
+        Err(_) => return String::new(),
+    };
+
+    unsafe {
+        let now = libc::time(ptr::null_mut());
+        let mut tm: libc::tm = mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return String::new();
+        }
+
+        let mut buffer = [0u8; 256];
+        let len = libc::strftime(
+            buffer.as_mut_ptr() as *mut libc::c_char,
+            buffer.len(),
+            format.as_ptr(),
+            &tm,
+        );
+
+        String::from_utf8_lossy(&buffer[..len]).into_owned()
+    }
+}
+
+/// Whether the process runs as root or, if `group` is given, has that group among its groups.
+pub fn is_admin(group: Option<&str>) -> bool {
+    if unsafe { libc::geteuid() } == 0 {
+        return true;
+    }
+
+    group.map(is_in_group).unwrap_or(false)
+}
+
+fn is_in_group(name: &str) -> bool {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        let group = libc::getgrnam(name.as_ptr());
+        if group.is_null() {
+            return false;
+        }
+        let gid = (*group).gr_gid;
+
+        if libc::getegid() == gid {
+            return true;
+        }
+
+        let len = libc::getgroups(0, ptr::null_mut());
+        if len <= 0 {
+            return false;
+        }
+
+        let mut groups = vec![0; len as usize];
+        let len = libc::getgroups(len, groups.as_mut_ptr());
+        groups.truncate(len.max(0) as usize);
+
+        groups.contains(&gid)
+    }
+}
@@ -0,0 +1,64 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// The number of columns a grapheme cluster takes up, two for wide East Asian characters and emoji.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width()
+}
+
+/// The number of columns `text` takes up in the terminal.
+pub fn width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Splits `text` into its grapheme clusters, which the user sees as one character each.
+pub fn graphemes(text: &str) -> impl DoubleEndedIterator<Item = &str> {
+    text.graphemes(true)
+}
+
+/// Returns the column and the number of rows further down the terminal continues at, after
+/// writing `text` at `column` of a row with `columns` columns. A wide character which does not
+/// fit anymore starts the next row, and a full row continues at the start of the next one.
+pub fn advance(text: &str, column: usize, columns: usize) -> (usize, usize) {
+    let columns = columns.max(1);
+    let mut column = column;
+    let mut rows = 0;
+    for grapheme in text.graphemes(true) {
+        let width = grapheme_width(grapheme);
+        if column + width > columns {
+            column = 0;
+            rows += 1;
+        }
+
+        column += width;
+        if column >= columns {
+            column = 0;
+            rows += 1;
+        }
+    }
+
+    (column, rows)
+}
+
+/// Returns the longest end of `text` which fits into `columns`.
+pub fn last_columns(text: &str, columns: usize) -> &str {
+    let mut width = 0;
+    let mut start = text.len();
+    for (index, grapheme) in text.grapheme_indices(true).rev() {
+        width += grapheme_width(grapheme);
+        if width > columns {
+            break;
+        }
+        start = index;
+    }
+
+    &text[start..]
+}
+
+/// Returns the byte offset of the grapheme cluster at `index`, or the length behind the last one.
+pub fn byte_offset(text: &str, index: usize) -> usize {
+    text.grapheme_indices(true)
+        .nth(index)
+        .map(|(offset, _)| offset)
+        .unwrap_or(text.len())
+}
@@ -0,0 +1,194 @@
+use crate::config::{Config, Rgb};
+use crate::shell::prompt;
+use log::{debug, warn};
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const BUNDLED: [(&str, &str); 3] = [
+    ("default", include_str!("../themes/default.toml")),
+    ("gruvbox", include_str!("../themes/gruvbox.toml")),
+    ("solarized", include_str!("../themes/solarized.toml")),
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PromptColors {
+    pub user: Option<Rgb>,
+    pub host: Option<Rgb>,
+    pub time: Option<Rgb>,
+    pub venv: Option<Rgb>,
+    pub env: Option<Rgb>,
+    pub ssh: Option<Rgb>,
+    pub location: Option<Rgb>,
+    pub git: Option<Rgb>,
+    pub status: Option<Rgb>,
+    pub duration: Option<Rgb>,
+    pub caret: Option<Rgb>,
+    pub admin: Option<Rgb>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SyntaxColors {
+    pub command: Option<Rgb>,
+    pub text: Option<Rgb>,
+    pub selection: Option<Rgb>,
+}
+
+fn materialize<T: Default>(config: &mut Option<T>) {
+    config.get_or_insert_with(T::default);
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Theme {
+    #[serde(default)]
+    pub prompt: PromptColors,
+    #[serde(default)]
+    pub syntax: SyntaxColors,
+}
+
+impl Theme {
+    /// Fills every color the user did not configure with the color of this theme.
+    pub fn apply_to(&self, config: &mut Config) {
+        // Widgets the prompt creates without a config are colored like the others
+        let names: Vec<String> = prompt::fallback_widgets(&config.prompt)
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        let prompt = &mut config.prompt;
+        for name in names {
+            match name.as_str() {
+                "user" => materialize(&mut prompt.user),
+                "host" => materialize(&mut prompt.host),
+                "time" => materialize(&mut prompt.time),
+                "venv" => materialize(&mut prompt.venv),
+                "env" => materialize(&mut prompt.env),
+                "ssh" => materialize(&mut prompt.ssh),
+                "git" => materialize(&mut prompt.git),
+                "status" => materialize(&mut prompt.status),
+                "duration" => materialize(&mut prompt.duration),
+                _ => {}
+            }
+        }
+
+        if let Some(ref mut user) = prompt.user {
+            user.color = user.color.or(self.prompt.user);
+        }
+        if let Some(ref mut host) = prompt.host {
+            host.color = host.color.or(self.prompt.host);
+        }
+        if let Some(ref mut time) = prompt.time {
+            time.color = time.color.or(self.prompt.time);
+        }
+        if let Some(ref mut venv) = prompt.venv {
+            venv.color = venv.color.or(self.prompt.venv);
+        }
+        if let Some(ref mut env) = prompt.env {
+            env.color = env.color.or(self.prompt.env);
+        }
+        if let Some(ref mut ssh) = prompt.ssh {
+            ssh.color = ssh.color.or(self.prompt.ssh);
+        }
+        if let Some(ref mut git) = prompt.git {
+            git.color = git.color.or(self.prompt.git);
+        }
+        if let Some(ref mut status) = prompt.status {
+            status.color = status.color.or(self.prompt.status);
+        }
+        if let Some(ref mut duration) = prompt.duration {
+            duration.color = duration.color.or(self.prompt.duration);
+        }
+        prompt.location.color = prompt.location.color.or(self.prompt.location);
+        prompt.caret.color = prompt.caret.color.or(self.prompt.caret);
+        prompt.caret.admin_color = prompt.caret.admin_color.or(self.prompt.admin);
+
+        let colorize = &mut config.colorize;
+        colorize.command = colorize.command.or(self.syntax.command);
+        colorize.text = colorize.text.or(self.syntax.text);
+        colorize.selection = colorize.selection.or(self.syntax.selection);
+    }
+}
+
+fn user_theme_dir() -> Option<PathBuf> {
+    use std::env;
+
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("mysh").join("themes"))
+}
+
+pub struct Themes {
+    themes: BTreeMap<String, Theme>,
+}
+
+impl Themes {
+    /// The bundled themes and the ones of the user.
+    pub fn load() -> Self {
+        Self::with_dir(user_theme_dir())
+    }
+
+    /// The bundled themes and the ones in `dir`, which replace bundled ones of the same name.
+    pub fn with_dir(dir: Option<PathBuf>) -> Self {
+        let mut themes = BTreeMap::new();
+        for (name, content) in BUNDLED.iter() {
+            let theme = toml::from_str(content).expect("Unable to parse bundled theme");
+            themes.insert((*name).to_owned(), theme);
+        }
+
+        if let Some(dir) = dir {
+            Self::load_dir(dir, &mut themes);
+        }
+
+        Self { themes }
+    }
+
+    fn load_dir(dir: PathBuf, themes: &mut BTreeMap<String, Theme>) {
+        use std::fs;
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                continue;
+            }
+
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+
+            match fs::read_to_string(&path).map(|content| toml::from_str(&content)) {
+                Ok(Ok(theme)) => {
+                    debug!("Load theme {} from {:?}", name, path);
+                    themes.insert(name, theme);
+                }
+                Ok(Err(e)) => warn!("Unable to parse theme {:?}: {}", path, e),
+                Err(e) => warn!("Unable to read theme {:?}: {}", path, e),
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.themes.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.themes.keys().map(String::as_str)
+    }
+
+    /// Returns a copy of `config` with the colors of the named theme beneath the user's own.
+    pub fn apply(&self, name: &str, config: &Config) -> Config {
+        let mut config = config.clone();
+        if let Some(theme) = self.get(name) {
+            theme.apply_to(&mut config);
+        } else {
+            warn!("Unknown theme {}", name);
+        }
+
+        config
+    }
+}